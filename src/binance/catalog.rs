@@ -0,0 +1,410 @@
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use crate::binance::historical::{ArchiveFrequency, Dataset, Market, generate_daily_date_range};
+use crate::errors::Error;
+use crate::fs::read::identify_files_recursively;
+
+/// Archive stored locally, identified from its path, i.e.
+/// `<market>/<frequency>/<dataset>/<symbol>[/<interval>]/<file>.zip`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    pub market: Market,
+    pub frequency: ArchiveFrequency,
+    pub dataset: Dataset,
+    pub symbol: String,
+    /// First day of the period covered by the archive.
+    pub date: NaiveDate,
+    pub path: PathBuf,
+    /// Size of the archive in bytes.
+    pub size: u64,
+}
+
+impl ArchiveEntry {
+    /// Last day of the period covered by the archive.
+    pub fn last_day(&self) -> NaiveDate {
+        self.frequency.period_end(&self.date).unwrap_or(self.date)
+    }
+
+    /// Whether the period covered by the archive overlaps `start..=end`.
+    pub fn overlaps(&self, start: Option<NaiveDate>, end: Option<NaiveDate>) -> bool {
+        start.is_none_or(|start| self.last_day() >= start) && end.is_none_or(|end| self.date <= end)
+    }
+}
+
+/// Identifies an archive from its path relative to `root`.
+fn parse_archive_path(root: &Path, path: &Path) -> Option<ArchiveEntry> {
+    let relative = path
+        .strip_prefix(root)
+        .ok()?
+        .iter()
+        .map(|component| component.to_str())
+        .collect::<Option<Vec<&str>>>()?
+        .join("/");
+
+    let (market, rest) = Market::ALL.into_iter().find_map(|market| {
+        relative
+            .strip_prefix(&format!("{market}/"))
+            .map(|rest| (market, rest))
+    })?;
+
+    let parts: Vec<&str> = rest.split('/').collect();
+    let (frequency, name, symbol, interval, file) = match parts.as_slice() {
+        [frequency, name, symbol, file] => (*frequency, *name, *symbol, None, *file),
+        [frequency, name, symbol, interval, file] => {
+            (*frequency, *name, *symbol, Some(*interval), *file)
+        }
+        _ => return None,
+    };
+
+    let frequency = [ArchiveFrequency::Daily, ArchiveFrequency::Monthly]
+        .into_iter()
+        .find(|f| f.to_string() == frequency)?;
+    let dataset = Dataset::from_parts(name, interval)?;
+
+    let period = file
+        .strip_suffix(".zip")?
+        .strip_prefix(&dataset.file_prefix(symbol))?
+        .strip_prefix('-')?;
+    let date = match frequency {
+        ArchiveFrequency::Daily => NaiveDate::parse_from_str(period, "%Y-%m-%d"),
+        ArchiveFrequency::Monthly => NaiveDate::parse_from_str(&format!("{period}-01"), "%Y-%m-%d"),
+    }
+    .ok()?;
+    // Rejects periods which are not zero padded, e.g. `2024-1`.
+    if date.format(frequency.date_format()).to_string() != period {
+        return None;
+    }
+
+    Some(ArchiveEntry {
+        market,
+        frequency,
+        dataset,
+        symbol: symbol.to_string(),
+        date,
+        path: path.to_path_buf(),
+        size: 0,
+    })
+}
+
+/// Selects archives from a catalog, unspecified fields match any archive.
+#[derive(Debug, Clone, Default)]
+pub struct CatalogFilter {
+    pub market: Option<Market>,
+    pub symbol: Option<String>,
+    pub dataset: Option<Dataset>,
+    pub frequency: Option<ArchiveFrequency>,
+    /// Archives covering a period ending before this day are excluded.
+    pub start: Option<NaiveDate>,
+    /// Archives covering a period starting after this day are excluded.
+    pub end: Option<NaiveDate>,
+}
+
+impl CatalogFilter {
+    pub fn matches(&self, archive: &ArchiveEntry) -> bool {
+        self.market.is_none_or(|market| market == archive.market)
+            && self
+                .symbol
+                .as_ref()
+                .is_none_or(|symbol| symbol.eq_ignore_ascii_case(&archive.symbol))
+            && self
+                .dataset
+                .as_ref()
+                .is_none_or(|dataset| dataset == &archive.dataset)
+            && self
+                .frequency
+                .is_none_or(|frequency| frequency == archive.frequency)
+            && archive.overlaps(self.start, self.end)
+    }
+}
+
+/// Days covered by the archives of a single market, symbol and dataset.
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    pub market: Market,
+    pub symbol: String,
+    pub dataset: Dataset,
+    /// First day covered by any archive.
+    pub first: NaiveDate,
+    /// Last day covered by any archive.
+    pub last: NaiveDate,
+    pub archives: usize,
+    /// Total size of the archives in bytes.
+    pub size: u64,
+    /// Ranges of days (inclusive) between `first` and `last` not
+    /// covered by any archive.
+    pub missing: Vec<(NaiveDate, NaiveDate)>,
+}
+
+impl Coverage {
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    pub fn missing_days(&self) -> i64 {
+        self.missing
+            .iter()
+            .map(|(from, to)| (*to - *from).num_days() + 1)
+            .sum()
+    }
+}
+
+impl std::fmt::Display for Coverage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{}: {} to {}, {} archives, {} bytes, {} missing days",
+            self.market,
+            self.dataset.directory(&self.symbol),
+            self.first,
+            self.last,
+            self.archives,
+            self.size,
+            self.missing_days()
+        )
+    }
+}
+
+/// Days covered by any of `archives`.
+fn covered_days<'a, I>(archives: I) -> BTreeSet<NaiveDate>
+where
+    I: IntoIterator<Item = &'a ArchiveEntry>,
+{
+    archives
+        .into_iter()
+        .flat_map(|archive| generate_daily_date_range(archive.date, archive.last_day()))
+        .collect()
+}
+
+/// Ranges of consecutive days within `first..=last` absent from `days`.
+fn missing_ranges(
+    days: &BTreeSet<NaiveDate>,
+    first: NaiveDate,
+    last: NaiveDate,
+) -> Vec<(NaiveDate, NaiveDate)> {
+    let mut missing: Vec<(NaiveDate, NaiveDate)> = Vec::new();
+    for day in generate_daily_date_range(first, last).filter(|day| !days.contains(day)) {
+        match missing.last_mut() {
+            Some((_, to)) if to.succ_opt() == Some(day) => *to = day,
+            _ => missing.push((day, day)),
+        }
+    }
+    missing
+}
+
+/// Inventory of the archives stored locally, e.g. under `data/`.
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    pub root: PathBuf,
+    /// Recognised archives, sorted by path.
+    pub archives: Vec<ArchiveEntry>,
+    /// Archives whose path does not follow the expected layout.
+    pub unrecognised: Vec<PathBuf>,
+}
+
+impl Catalog {
+    /// Walks `root`, identifying every archive from its path. Checksums and
+    /// other files are ignored. A missing `root` results in an empty catalog.
+    pub fn scan<P: AsRef<Path>>(root: P) -> Result<Self, Error> {
+        let root = root.as_ref();
+        let mut catalog = Self {
+            root: root.to_path_buf(),
+            ..Default::default()
+        };
+        if !root.is_dir() {
+            return Ok(catalog);
+        }
+
+        let archives = identify_files_recursively(root)?
+            .into_iter()
+            .filter(|path| path.extension().is_some_and(|extension| extension == "zip"));
+
+        for path in archives {
+            match parse_archive_path(root, &path) {
+                Some(mut archive) => {
+                    archive.size = std::fs::metadata(&path)?.len();
+                    catalog.archives.push(archive);
+                }
+                None => catalog.unrecognised.push(path),
+            }
+        }
+        Ok(catalog)
+    }
+
+    /// Total size of the archives in bytes.
+    pub fn total_size(&self) -> u64 {
+        self.archives.iter().map(|archive| archive.size).sum()
+    }
+
+    pub fn symbols(&self) -> BTreeSet<&str> {
+        self.archives
+            .iter()
+            .map(|archive| archive.symbol.as_str())
+            .collect()
+    }
+
+    /// Archives matching `filter`, sorted by the first day they cover.
+    pub fn select(&self, filter: &CatalogFilter) -> Vec<&ArchiveEntry> {
+        let mut archives: Vec<&ArchiveEntry> = self
+            .archives
+            .iter()
+            .filter(|archive| filter.matches(archive))
+            .collect();
+        archives.sort_by_key(|archive| (archive.date, archive.frequency));
+        archives
+    }
+
+    /// Spot kline archives of `symbol` and `interval` covering any day
+    /// within `start..=end`.
+    pub fn klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Vec<&ArchiveEntry> {
+        self.select(&CatalogFilter {
+            market: Some(Market::Spot),
+            symbol: Some(symbol.to_string()),
+            dataset: Some(Dataset::Klines(interval.to_string())),
+            start: Some(start),
+            end: Some(end),
+            ..Default::default()
+        })
+    }
+
+    /// Days covered by the archives matching `filter`, by either
+    /// monthly or daily archives.
+    pub fn covered_days(&self, filter: &CatalogFilter) -> BTreeSet<NaiveDate> {
+        covered_days(self.select(filter))
+    }
+
+    /// Coverage per market, symbol and dataset.
+    pub fn coverage(&self) -> Vec<Coverage> {
+        let mut groups: BTreeMap<(Market, &str, &Dataset), Vec<&ArchiveEntry>> = BTreeMap::new();
+        for archive in &self.archives {
+            groups
+                .entry((archive.market, &archive.symbol, &archive.dataset))
+                .or_default()
+                .push(archive);
+        }
+
+        groups
+            .into_iter()
+            .filter_map(|((market, symbol, dataset), archives)| {
+                let days = covered_days(archives.iter().copied());
+                let first = *days.first()?;
+                let last = *days.last()?;
+                Some(Coverage {
+                    market,
+                    symbol: symbol.to_string(),
+                    dataset: dataset.clone(),
+                    first,
+                    last,
+                    archives: archives.len(),
+                    size: archives.iter().map(|archive| archive.size).sum(),
+                    missing: missing_ranges(&days, first, last),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn parse(relative: &str) -> Option<ArchiveEntry> {
+        let root = Path::new("data");
+        parse_archive_path(root, &root.join(relative))
+    }
+
+    #[test]
+    fn parse_kline_archives() {
+        let daily = parse("spot/daily/klines/BTCUSDT/1h/BTCUSDT-1h-2025-06-03.zip").unwrap();
+        assert_eq!(daily.market, Market::Spot);
+        assert_eq!(daily.frequency, ArchiveFrequency::Daily);
+        assert_eq!(daily.dataset, Dataset::Klines("1h".to_string()));
+        assert_eq!(daily.symbol, "BTCUSDT");
+        assert_eq!(daily.date, date(2025, 6, 3));
+        assert_eq!(daily.last_day(), date(2025, 6, 3));
+
+        let monthly = parse("futures/um/monthly/klines/ETHUSDT/1d/ETHUSDT-1d-2024-02.zip").unwrap();
+        assert_eq!(monthly.market, Market::UsdMFutures);
+        assert_eq!(monthly.frequency, ArchiveFrequency::Monthly);
+        assert_eq!(monthly.date, date(2024, 2, 1));
+        assert_eq!(monthly.last_day(), date(2024, 2, 29));
+    }
+
+    #[test]
+    fn parse_archives_without_interval() {
+        let trades =
+            parse("futures/cm/daily/aggTrades/BTCUSD_PERP/BTCUSD_PERP-aggTrades-2025-01-31.zip")
+                .unwrap();
+        assert_eq!(trades.market, Market::CoinMFutures);
+        assert_eq!(trades.dataset, Dataset::AggTrades);
+        assert_eq!(trades.symbol, "BTCUSD_PERP");
+        assert_eq!(trades.date, date(2025, 1, 31));
+    }
+
+    #[test]
+    fn unrecognised_archive_paths() {
+        // Checksums, unknown markets and datasets, mismatched symbols or
+        // intervals and periods which are invalid or not zero padded.
+        for relative in [
+            "spot/daily/klines/BTCUSDT/1h/BTCUSDT-1h-2025-06-03.zip.CHECKSUM",
+            "options/daily/klines/BTCUSDT/1h/BTCUSDT-1h-2025-06-03.zip",
+            "spot/daily/candles/BTCUSDT/1h/BTCUSDT-1h-2025-06-03.zip",
+            "spot/daily/klines/BTCUSDT/1h/ETHUSDT-1h-2025-06-03.zip",
+            "spot/daily/klines/BTCUSDT/1h/BTCUSDT-4h-2025-06-03.zip",
+            "spot/daily/klines/BTCUSDT/1h/BTCUSDT-1h-2025-06-31.zip",
+            "spot/monthly/klines/BTCUSDT/1h/BTCUSDT-1h-2025-6.zip",
+            "spot/monthly/klines/BTCUSDT/1h/BTCUSDT-1h-2025-06-03.zip",
+            "spot/daily/aggTrades/BTCUSDT/1h/BTCUSDT-aggTrades-2025-06-03.zip",
+        ] {
+            assert_eq!(parse(relative), None, "{relative}");
+        }
+    }
+
+    #[test]
+    fn missing_ranges_between_covered_days() {
+        let days: BTreeSet<NaiveDate> = [
+            date(2025, 6, 1),
+            date(2025, 6, 2),
+            date(2025, 6, 5),
+            date(2025, 6, 7),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            missing_ranges(&days, date(2025, 5, 31), date(2025, 6, 9)),
+            vec![
+                (date(2025, 5, 31), date(2025, 5, 31)),
+                (date(2025, 6, 3), date(2025, 6, 4)),
+                (date(2025, 6, 6), date(2025, 6, 6)),
+                (date(2025, 6, 8), date(2025, 6, 9)),
+            ]
+        );
+        assert!(missing_ranges(&days, date(2025, 6, 1), date(2025, 6, 2)).is_empty());
+    }
+
+    #[test]
+    fn monthly_and_daily_archives_combine() {
+        let archives = [
+            parse("spot/monthly/klines/BTCUSDT/1h/BTCUSDT-1h-2025-04.zip").unwrap(),
+            parse("spot/daily/klines/BTCUSDT/1h/BTCUSDT-1h-2025-05-02.zip").unwrap(),
+        ];
+        let days = covered_days(&archives);
+        assert_eq!(days.len(), 31);
+        assert_eq!(
+            missing_ranges(&days, date(2025, 4, 1), date(2025, 5, 2)),
+            vec![(date(2025, 5, 1), date(2025, 5, 1))]
+        );
+    }
+}
@@ -0,0 +1,374 @@
+use std::collections::BTreeMap;
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::value::RawValue;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+use crate::binance::connection::{
+    ConnectionEvent, ReconnectOptions, StreamItem, run_connection, spawn_connection,
+};
+use crate::binance::stream::{BASE_END_POINT, MarketStream};
+use crate::binance::trade_book::{DiffDepthStream, PartialDepthStream};
+use crate::errors::Error;
+use crate::models::{
+    AggTradeEvent, AvgPriceEvent, BookTickerEvent, KlineEvent, MiniTickerEvent,
+    RollingWindowTickerEvent, TickerEvent, TradeEvent,
+};
+
+// Maximum number of streams a single connection may subscribe to.
+const MAX_STREAMS: usize = 1024;
+
+// Events received over a combined stream are wrapped in an envelope
+// identifying the stream, i.e. `{"stream":"<name>","data":<event>}`.
+#[derive(Deserialize)]
+struct Envelope<'a> {
+    stream: &'a str,
+    #[serde(borrow)]
+    data: &'a RawValue,
+}
+
+/// Event of any market stream, see `CombinedStream::route_tagged`.
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    Trade(TradeEvent),
+    AggTrade(AggTradeEvent),
+    Kline(KlineEvent),
+    MiniTicker(MiniTickerEvent),
+    Ticker(TickerEvent),
+    RollingWindowTicker(RollingWindowTickerEvent),
+    AvgPrice(AvgPriceEvent),
+    BookTicker(BookTickerEvent),
+    PartialDepth(PartialDepthStream),
+    DiffDepth(DiffDepthStream),
+    /// Change in the state of the connection shared by every stream.
+    Connection(ConnectionEvent),
+}
+
+impl From<TradeEvent> for StreamEvent {
+    fn from(value: TradeEvent) -> Self {
+        Self::Trade(value)
+    }
+}
+
+impl From<AggTradeEvent> for StreamEvent {
+    fn from(value: AggTradeEvent) -> Self {
+        Self::AggTrade(value)
+    }
+}
+
+impl From<KlineEvent> for StreamEvent {
+    fn from(value: KlineEvent) -> Self {
+        Self::Kline(value)
+    }
+}
+
+impl From<MiniTickerEvent> for StreamEvent {
+    fn from(value: MiniTickerEvent) -> Self {
+        Self::MiniTicker(value)
+    }
+}
+
+impl From<TickerEvent> for StreamEvent {
+    fn from(value: TickerEvent) -> Self {
+        Self::Ticker(value)
+    }
+}
+
+impl From<RollingWindowTickerEvent> for StreamEvent {
+    fn from(value: RollingWindowTickerEvent) -> Self {
+        Self::RollingWindowTicker(value)
+    }
+}
+
+impl From<AvgPriceEvent> for StreamEvent {
+    fn from(value: AvgPriceEvent) -> Self {
+        Self::AvgPrice(value)
+    }
+}
+
+impl From<BookTickerEvent> for StreamEvent {
+    fn from(value: BookTickerEvent) -> Self {
+        Self::BookTicker(value)
+    }
+}
+
+impl From<PartialDepthStream> for StreamEvent {
+    fn from(value: PartialDepthStream) -> Self {
+        Self::PartialDepth(value)
+    }
+}
+
+impl From<DiffDepthStream> for StreamEvent {
+    fn from(value: DiffDepthStream) -> Self {
+        Self::DiffDepth(value)
+    }
+}
+
+/// Event along with the name of the stream it was received on.
+#[derive(Debug, Clone)]
+pub struct TaggedEvent {
+    pub stream: String,
+    pub event: StreamEvent,
+}
+
+// Deserializes the data of an event and sends it, or a change in the state of
+// the connection, to a channel, returning `false` once the receiving end of
+// the channel has been closed.
+type Route = Box<dyn FnMut(StreamItem<&str>) -> Result<bool, Error> + Send>;
+
+/// Receives many market streams, of any symbol and type, over a single
+/// connection, routing the events of every stream to its own channel.
+///
+/// ```ignore
+/// let (trades_tx, trades_rx) = std::sync::mpsc::channel();
+/// let (tagged_tx, tagged_rx) = std::sync::mpsc::channel();
+/// let handle = CombinedStream::new()
+///     .route(MarketStream::trade("btcusdt"), trades_tx)
+///     .route_tagged(MarketStream::kline("ethusdt", &KlineInterval::OneMinute), tagged_tx.clone())
+///     .route_tagged(MarketStream::book_ticker("ethusdt"), tagged_tx)
+///     .spawn()?;
+/// ```
+#[derive(Default)]
+pub struct CombinedStream {
+    routes: BTreeMap<String, Vec<Route>>,
+    options: ReconnectOptions,
+}
+
+impl CombinedStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add_route(mut self, stream: &str, route: Route) -> Self {
+        self.routes
+            .entry(stream.to_string())
+            .or_default()
+            .push(route);
+        self
+    }
+
+    /// Controls how lost connections are re-established.
+    pub fn reconnect_options(mut self, options: ReconnectOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sends the events of `stream`, and the changes in the state of the
+    /// connection, to `sender`.
+    pub fn route<E>(self, stream: MarketStream<E>, sender: Sender<StreamItem<E>>) -> Self
+    where
+        E: DeserializeOwned + Send + 'static,
+    {
+        self.add_route(
+            stream.name(),
+            Box::new(move |item| Ok(sender.send(item.parse()?).is_ok())),
+        )
+    }
+
+    /// Sends the events of `stream`, tagged with the stream name, to `sender`.
+    /// Allows a single channel to receive the events of several streams.
+    /// Changes in the state of the connection are sent as
+    /// `StreamEvent::Connection`, once per stream.
+    pub fn route_tagged<E>(self, stream: MarketStream<E>, sender: Sender<TaggedEvent>) -> Self
+    where
+        E: DeserializeOwned + Into<StreamEvent> + Send + 'static,
+    {
+        let name = stream.name().to_string();
+        self.add_route(
+            stream.name(),
+            Box::new(move |item| {
+                let event = match item.parse::<E>()? {
+                    StreamItem::Event(event) => event.into(),
+                    StreamItem::Connection(event) => StreamEvent::Connection(event),
+                };
+                let tagged = TaggedEvent {
+                    stream: name.clone(),
+                    event,
+                };
+                Ok(sender.send(tagged).is_ok())
+            }),
+        )
+    }
+
+    /// Names of the streams routed.
+    pub fn streams(&self) -> impl Iterator<Item = &str> {
+        self.routes.keys().map(String::as_str)
+    }
+
+    fn end_point(&self) -> Result<Url, Error> {
+        let streams: Vec<&str> = self.streams().collect();
+        if streams.is_empty() {
+            return Err(Error::Other("No streams to subscribe to".into()));
+        }
+        if streams.len() > MAX_STREAMS {
+            return Err(Error::Other(format!(
+                "Unable to subscribe to {} streams over a single connection, the maximum is {MAX_STREAMS}",
+                streams.len()
+            )));
+        }
+        let end_point = format!("{BASE_END_POINT}/stream?streams={}", streams.join("/"));
+        Url::parse(&end_point).map_err(|e| Error::Other(e.to_string()))
+    }
+
+    /// Sends an event to the routes of its stream, and changes in the state
+    /// of the connection to every route, returning `false` once the
+    /// receivers of every stream are closed. An event which can't be
+    /// deserialized is skipped, rather than closing the connection shared
+    /// by every stream.
+    fn dispatch(&mut self, item: StreamItem<&str>) -> bool {
+        fn send(routes: &mut Vec<Route>, item: StreamItem<&str>) {
+            routes.retain_mut(|route| route(item.clone()).unwrap_or(true));
+        }
+
+        match item {
+            StreamItem::Event(text) => {
+                // Responses to requests (e.g. subscriptions) are not wrapped.
+                let Ok(envelope) = serde_json::from_str::<Envelope>(text) else {
+                    return true;
+                };
+                if let Some(routes) = self.routes.get_mut(envelope.stream) {
+                    send(routes, StreamItem::Event(envelope.data.get()));
+                }
+            }
+            StreamItem::Connection(event) => {
+                for routes in self.routes.values_mut() {
+                    send(routes, StreamItem::Connection(event.clone()));
+                }
+            }
+        }
+        self.routes.retain(|_, routes| !routes.is_empty());
+        !self.routes.is_empty()
+    }
+
+    /// Connects on the current tokio runtime, routing the events received
+    /// until either `cancel` is cancelled or the receivers of every stream
+    /// are closed. Lost connections are re-established, subscribing to the
+    /// same streams.
+    pub async fn run(mut self, cancel: CancellationToken) -> Result<(), Error> {
+        let url = self.end_point()?;
+        let options = std::mem::take(&mut self.options);
+        run_connection(&url, &options, &cancel, |item| Ok(self.dispatch(item))).await
+    }
+
+    /// Similar to `run`, however connects on a separate thread.
+    pub fn spawn(mut self) -> Result<thread::JoinHandle<Result<(), Error>>, Error> {
+        let url = self.end_point()?;
+        let options = std::mem::take(&mut self.options);
+        Ok(spawn_connection(url, options, move |item| {
+            Ok(self.dispatch(item))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::BookTickerEvent;
+    use std::sync::mpsc::{Receiver, channel};
+
+    fn book_ticker(symbol: &str, bid: &str) -> String {
+        format!(
+            r#"{{"stream":"{}@bookTicker","data":{{"u":400900217,"s":"{}","b":"{bid}","B":"31.21","a":"25.36","A":"40.66"}}}}"#,
+            symbol.to_lowercase(),
+            symbol.to_uppercase()
+        )
+    }
+
+    fn bid(receiver: &Receiver<StreamItem<BookTickerEvent>>) -> Option<f64> {
+        match receiver.try_recv().ok()? {
+            StreamItem::Event(event) => Some(event.b),
+            StreamItem::Connection(_) => None,
+        }
+    }
+
+    #[test]
+    fn dispatch_unwraps_envelope() {
+        let (btc_tx, btc_rx) = channel();
+        let (tagged_tx, tagged_rx) = channel();
+        let mut combined = CombinedStream::new()
+            .route(MarketStream::book_ticker("btcusdt"), btc_tx)
+            .route_tagged(MarketStream::book_ticker("ethusdt"), tagged_tx);
+
+        assert!(combined.dispatch(StreamItem::Event(&book_ticker("btcusdt", "25.35"))));
+        assert!(combined.dispatch(StreamItem::Event(&book_ticker("ethusdt", "1.5"))));
+        assert_eq!(bid(&btc_rx), Some(25.35));
+        let tagged = tagged_rx.try_recv().unwrap();
+        assert_eq!(tagged.stream, "ethusdt@bookTicker");
+        assert!(matches!(tagged.event, StreamEvent::BookTicker(event) if event.s == "ETHUSDT"));
+    }
+
+    #[test]
+    fn dispatch_ignores_unknown_streams_and_responses() {
+        let (sender, receiver) = channel();
+        let mut combined =
+            CombinedStream::new().route(MarketStream::book_ticker("btcusdt"), sender);
+
+        assert!(combined.dispatch(StreamItem::Event(&book_ticker("solusdt", "1.0"))));
+        assert!(combined.dispatch(StreamItem::Event(r#"{"result":null,"id":1}"#)));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn dispatch_skips_invalid_events() {
+        let (sender, receiver) = channel();
+        let mut combined =
+            CombinedStream::new().route(MarketStream::book_ticker("btcusdt"), sender);
+
+        assert!(combined.dispatch(StreamItem::Event(&book_ticker("btcusdt", "not a price"))));
+        assert!(receiver.try_recv().is_err());
+        // The route remains, hence later events are still received.
+        assert!(combined.dispatch(StreamItem::Event(&book_ticker("btcusdt", "25.35"))));
+        assert_eq!(bid(&receiver), Some(25.35));
+    }
+
+    #[test]
+    fn dispatch_connection_events_in_order() {
+        let (btc_tx, btc_rx) = channel();
+        let (tagged_tx, tagged_rx) = channel();
+        let mut combined = CombinedStream::new()
+            .route(MarketStream::book_ticker("btcusdt"), btc_tx)
+            .route_tagged(MarketStream::book_ticker("ethusdt"), tagged_tx);
+
+        assert!(combined.dispatch(StreamItem::Event(&book_ticker("btcusdt", "25.35"))));
+        let disconnected = ConnectionEvent::Disconnected("Connection closed".to_string());
+        assert!(combined.dispatch(StreamItem::Connection(disconnected)));
+        assert!(combined.dispatch(StreamItem::Event(&book_ticker("btcusdt", "25.40"))));
+
+        assert_eq!(bid(&btc_rx), Some(25.35));
+        assert!(matches!(
+            btc_rx.try_recv().unwrap(),
+            StreamItem::Connection(ConnectionEvent::Disconnected(_))
+        ));
+        assert_eq!(bid(&btc_rx), Some(25.40));
+        let tagged = tagged_rx.try_recv().unwrap();
+        assert!(matches!(
+            tagged.event,
+            StreamEvent::Connection(ConnectionEvent::Disconnected(_))
+        ));
+    }
+
+    #[test]
+    fn dispatch_removes_closed_routes() {
+        let (btc_tx, btc_rx) = channel();
+        let (eth_tx, eth_rx) = channel();
+        let mut combined = CombinedStream::new()
+            .route(MarketStream::book_ticker("btcusdt"), btc_tx)
+            .route(MarketStream::book_ticker("ethusdt"), eth_tx);
+
+        drop(btc_rx);
+        assert!(combined.dispatch(StreamItem::Event(&book_ticker("btcusdt", "25.35"))));
+        assert_eq!(
+            combined.streams().collect::<Vec<_>>(),
+            vec!["ethusdt@bookTicker"]
+        );
+
+        // Ends once the receivers of every stream are closed.
+        drop(eth_rx);
+        assert!(!combined.dispatch(StreamItem::Connection(ConnectionEvent::Connected)));
+        assert_eq!(combined.streams().count(), 0);
+    }
+}
@@ -0,0 +1,221 @@
+use futures_util::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::thread;
+use std::time::Duration;
+
+use tokio_tungstenite::connect_async;
+use tokio_util::sync::CancellationToken;
+use tungstenite::Message;
+use url::Url;
+
+use crate::errors::Error;
+
+/// Changes in the state of a websocket connection. Events may be missed
+/// between `Disconnected` and the subsequent `Connected`, hence state which
+/// depends on continuity (e.g. a local order book) should be invalidated.
+#[derive(Debug, Clone)]
+pub enum ConnectionEvent {
+    Connected,
+    Disconnected(String),
+    Reconnecting { attempt: u32, delay: Duration },
+}
+
+impl std::fmt::Display for ConnectionEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Connected => write!(f, "Connected"),
+            Self::Disconnected(reason) => write!(f, "Disconnected: {reason}"),
+            Self::Reconnecting { attempt, delay } => {
+                write!(f, "Reconnecting (attempt {attempt}) in {delay:?}")
+            }
+        }
+    }
+}
+
+/// Received over a websocket connection, in the order it occurred. Changes
+/// in the state of the connection are part of the stream, so that state
+/// depending on continuity is invalidated before any subsequent event.
+#[derive(Debug, Clone)]
+pub enum StreamItem<E> {
+    Event(E),
+    Connection(ConnectionEvent),
+}
+
+impl StreamItem<&str> {
+    /// Deserializes the text of an event.
+    pub(crate) fn parse<E: DeserializeOwned>(self) -> Result<StreamItem<E>, Error> {
+        match self {
+            Self::Event(text) => Ok(StreamItem::Event(serde_json::from_str(text)?)),
+            Self::Connection(event) => Ok(StreamItem::Connection(event)),
+        }
+    }
+}
+
+/// Controls how a lost websocket connection is re-established.
+#[derive(Debug, Clone)]
+pub struct ReconnectOptions {
+    /// Delay before the first reconnection attempt, doubled after every
+    /// failed attempt, and randomised to avoid reconnecting in lockstep.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Consecutive failed attempts after which the connection is given up,
+    /// unlimited if `None`.
+    pub max_attempts: Option<u32>,
+    /// Binance closes every connection after 24 hours, hence connections
+    /// are re-established in advance once open for this long.
+    pub max_lifetime: Duration,
+}
+
+impl Default for ReconnectOptions {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            max_attempts: None,
+            max_lifetime: Duration::from_secs(23 * 60 * 60),
+        }
+    }
+}
+
+impl ReconnectOptions {
+    /// Delay before the specified reconnection attempt (starting at 1),
+    /// randomly chosen between half and the full exponential backoff.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff);
+        let jitter = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        backoff.mul_f64(0.5 + jitter / 2.0)
+    }
+}
+
+/// Keeps a connection to `url` open, passing the text of every message
+/// received, and every change in the state of the connection, to `handle`
+/// until it returns `false`, or until `cancel` is cancelled. Lost connections
+/// are re-established, which also re-subscribes, since the streams are part
+/// of `url`. Ping frames are answered as soon as they are received.
+pub(crate) async fn run_connection<F>(
+    url: &Url,
+    options: &ReconnectOptions,
+    cancel: &CancellationToken,
+    mut handle: F,
+) -> Result<(), Error>
+where
+    F: FnMut(StreamItem<&str>) -> Result<bool, Error>,
+{
+    let mut attempt = 0;
+    loop {
+        let connection = tokio::select! {
+            _ = cancel.cancelled() => return Ok(()),
+            connection = connect_async(url.clone()) => connection,
+        };
+
+        match connection {
+            Ok((mut socket, _)) => {
+                if !handle(StreamItem::Connection(ConnectionEvent::Connected))? {
+                    let _ = socket.close(None).await;
+                    return Ok(());
+                }
+                let lifetime = tokio::time::sleep(options.max_lifetime);
+                tokio::pin!(lifetime);
+
+                let reason = loop {
+                    let message = tokio::select! {
+                        _ = cancel.cancelled() => {
+                            // Errors are ignored, the connection is discarded anyway.
+                            let _ = socket.close(None).await;
+                            return Ok(());
+                        }
+                        _ = &mut lifetime => {
+                            let _ = socket.close(None).await;
+                            break "Maximum connection lifetime reached".to_string();
+                        }
+                        message = socket.next() => match message {
+                            Some(message) => message,
+                            None => break "Connection closed".to_string(),
+                        },
+                    };
+
+                    match message {
+                        Ok(Message::Text(text)) => {
+                            // Only reset once data is received, so connections
+                            // closed straight away are backed off as well.
+                            attempt = 0;
+                            if !handle(StreamItem::Event(&text))? {
+                                let _ = socket.close(None).await;
+                                return Ok(());
+                            }
+                        }
+                        Ok(Message::Ping(_)) => {
+                            // Sends the pong queued while reading the ping.
+                            if let Err(e) = socket.flush().await {
+                                break e.to_string();
+                            }
+                        }
+                        Ok(Message::Close(frame)) => {
+                            break frame.map_or("Closed by server".to_string(), |frame| {
+                                format!("Closed by server: {}", frame.reason)
+                            });
+                        }
+                        Ok(_) => {}
+                        Err(e) => break e.to_string(),
+                    }
+                };
+                if !handle(StreamItem::Connection(ConnectionEvent::Disconnected(
+                    reason,
+                )))? {
+                    return Ok(());
+                }
+            }
+            Err(e) => {
+                if options.max_attempts.is_some_and(|max| attempt >= max) {
+                    return Err(e.into());
+                }
+            }
+        }
+
+        attempt += 1;
+        // The proactive reconnect is immediate, it has not failed.
+        let delay = if attempt == 1 {
+            Duration::ZERO
+        } else {
+            options.backoff(attempt - 1)
+        };
+        if !handle(StreamItem::Connection(ConnectionEvent::Reconnecting {
+            attempt,
+            delay,
+        }))? {
+            return Ok(());
+        }
+        tokio::select! {
+            _ = cancel.cancelled() => return Ok(()),
+            _ = tokio::time::sleep(delay) => {}
+        }
+    }
+}
+
+/// Runs `run_connection` on a separate thread, for consumers which are
+/// not running on a tokio runtime.
+pub(crate) fn spawn_connection<F>(
+    url: Url,
+    options: ReconnectOptions,
+    handle: F,
+) -> thread::JoinHandle<Result<(), Error>>
+where
+    F: FnMut(StreamItem<&str>) -> Result<bool, Error> + Send + 'static,
+{
+    thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        runtime.block_on(run_connection(
+            &url,
+            &options,
+            &CancellationToken::new(),
+            handle,
+        ))
+    })
+}
@@ -0,0 +1,307 @@
+use crate::binance::trade_book::OrderBook;
+
+// Basis points per unit, i.e. 1 bps = 0.01%.
+const BPS: f64 = 10_000.0;
+
+/// Side of the book an order is filled against, i.e. buy orders are
+/// filled against asks and sell orders against bids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+/// Size of an order, in either the base or the quote asset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderAmount {
+    Base(f64),
+    Quote(f64),
+}
+
+/// Cumulative quantity of the levels within a distance of the mid price.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DepthWithin {
+    pub bid_base: f64,
+    pub bid_quote: f64,
+    pub ask_base: f64,
+    pub ask_quote: f64,
+}
+
+impl DepthWithin {
+    /// Difference between the bid and ask quantity (in base asset),
+    /// relative to their sum, i.e. between -1 (only asks) and 1 (only bids).
+    pub fn imbalance(&self) -> Option<f64> {
+        let total = self.bid_base + self.ask_base;
+        (total > 0.0).then(|| (self.bid_base - self.ask_base) / total)
+    }
+}
+
+/// Expected outcome of filling an order against the levels of a book.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FillEstimate {
+    /// Quantity filled, in base asset.
+    pub base: f64,
+    /// Quantity filled, in quote asset.
+    pub quote: f64,
+    pub average_price: f64,
+    /// Difference between the average price and the mid price, positive
+    /// when the average price is worse than the mid price.
+    pub slippage_bps: f64,
+    /// Number of levels (partially) consumed.
+    pub levels: usize,
+    /// Whether the book contains sufficient quantity to fill the order.
+    pub complete: bool,
+}
+
+/// Analytics over the levels of an order book, either maintained live (see
+/// `maintain_order_book`) or built from a recorded depth snapshot using
+/// `OrderBook::from_snapshot`, which fails on levels that can't be parsed.
+pub trait Depth {
+    /// Bids, as (price, quantity), from the highest price down.
+    fn bid_levels(&self) -> impl Iterator<Item = (f64, f64)>;
+
+    /// Asks, as (price, quantity), from the lowest price up.
+    fn ask_levels(&self) -> impl Iterator<Item = (f64, f64)>;
+
+    fn mid_price(&self) -> Option<f64> {
+        let (bid, _) = self.bid_levels().next()?;
+        let (ask, _) = self.ask_levels().next()?;
+        Some((bid + ask) / 2.0)
+    }
+
+    /// Difference between the best ask and bid, relative to the mid price.
+    fn spread_bps(&self) -> Option<f64> {
+        let (bid, _) = self.bid_levels().next()?;
+        let (ask, _) = self.ask_levels().next()?;
+        Some((ask - bid) / ((bid + ask) / 2.0) * BPS)
+    }
+
+    /// Mid price weighted by the quantity of the opposite side of the top
+    /// of the book, i.e. closer to the ask when bids outweigh asks.
+    fn microprice(&self) -> Option<f64> {
+        let (bid, bid_quantity) = self.bid_levels().next()?;
+        let (ask, ask_quantity) = self.ask_levels().next()?;
+        let total = bid_quantity + ask_quantity;
+        (total > 0.0).then(|| (bid * ask_quantity + ask * bid_quantity) / total)
+    }
+
+    /// Cumulative quantity of the levels within `bps` of the mid price.
+    fn depth_within_bps(&self, bps: f64) -> Option<DepthWithin> {
+        let mid = self.mid_price()?;
+        let mut depth = DepthWithin::default();
+        for (price, quantity) in self
+            .bid_levels()
+            .take_while(|(price, _)| *price >= mid * (1.0 - bps / BPS))
+        {
+            depth.bid_base += quantity;
+            depth.bid_quote += price * quantity;
+        }
+        for (price, quantity) in self
+            .ask_levels()
+            .take_while(|(price, _)| *price <= mid * (1.0 + bps / BPS))
+        {
+            depth.ask_base += quantity;
+            depth.ask_quote += price * quantity;
+        }
+        Some(depth)
+    }
+
+    /// Imbalance of the levels within `bps` of the mid price, see `DepthWithin::imbalance`.
+    fn imbalance(&self, bps: f64) -> Option<f64> {
+        self.depth_within_bps(bps)?.imbalance()
+    }
+
+    /// Walks the levels of the opposite side of the book to estimate the
+    /// average price of a market order of `amount`. Returns `None` if
+    /// either side of the book is empty.
+    fn estimate_fill(&self, side: OrderSide, amount: OrderAmount) -> Option<FillEstimate> {
+        let mid = self.mid_price()?;
+        let levels: Box<dyn Iterator<Item = (f64, f64)> + '_> = match side {
+            OrderSide::Buy => Box::new(self.ask_levels()),
+            OrderSide::Sell => Box::new(self.bid_levels()),
+        };
+
+        let (mut base, mut quote, mut consumed) = (0.0, 0.0, 0);
+        let requested = match amount {
+            OrderAmount::Base(quantity) | OrderAmount::Quote(quantity) => quantity,
+        };
+        let mut remaining = requested;
+        for (price, quantity) in levels {
+            if remaining <= 0.0 {
+                break;
+            }
+            let filled = match amount {
+                OrderAmount::Base(_) => quantity.min(remaining),
+                OrderAmount::Quote(_) => quantity.min(remaining / price),
+            };
+            base += filled;
+            quote += filled * price;
+            remaining -= match amount {
+                OrderAmount::Base(_) => filled,
+                OrderAmount::Quote(_) => filled * price,
+            };
+            consumed += 1;
+        }
+        if base <= 0.0 {
+            return None;
+        }
+
+        let average_price = quote / base;
+        let slippage = match side {
+            OrderSide::Buy => average_price - mid,
+            OrderSide::Sell => mid - average_price,
+        };
+        Some(FillEstimate {
+            base,
+            quote,
+            average_price,
+            slippage_bps: slippage / mid * BPS,
+            levels: consumed,
+            // Allows for rounding errors when filling a quote quantity.
+            complete: remaining <= requested * 1e-9,
+        })
+    }
+}
+
+impl Depth for OrderBook {
+    fn bid_levels(&self) -> impl Iterator<Item = (f64, f64)> {
+        self.bids()
+            .map(|(price, quantity)| (price.to_f64(), quantity.to_f64()))
+    }
+
+    fn ask_levels(&self) -> impl Iterator<Item = (f64, f64)> {
+        self.asks()
+            .map(|(price, quantity)| (price.to_f64(), quantity.to_f64()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance::trade_book::PartialDepthStream;
+
+    fn levels(levels: &[(&str, &str)]) -> Vec<[String; 2]> {
+        levels
+            .iter()
+            .map(|(price, quantity)| [price.to_string(), quantity.to_string()])
+            .collect()
+    }
+
+    fn book() -> OrderBook {
+        let snapshot = PartialDepthStream {
+            last_update_id: 1,
+            bids: levels(&[("99", "2"), ("98", "3"), ("90", "10")]),
+            asks: levels(&[("101", "1"), ("102", "4"), ("120", "10")]),
+        };
+        OrderBook::from_snapshot("BTCUSDT", &snapshot).unwrap()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn top_of_book() {
+        let book = book();
+        assert_eq!(book.mid_price(), Some(100.0));
+        assert_close(book.spread_bps().unwrap(), 200.0);
+        // Bids outweigh asks, hence the microprice is closer to the ask.
+        assert_close(book.microprice().unwrap(), (99.0 * 1.0 + 101.0 * 2.0) / 3.0);
+    }
+
+    #[test]
+    fn depth_and_imbalance() {
+        let book = book();
+        let depth = book.depth_within_bps(300.0).unwrap();
+        assert_eq!(
+            depth,
+            DepthWithin {
+                bid_base: 5.0,
+                bid_quote: 492.0,
+                ask_base: 5.0,
+                ask_quote: 509.0,
+            }
+        );
+        assert_eq!(book.imbalance(300.0), Some(0.0));
+        // Only the best bid and ask are within 100 bps.
+        assert_close(book.imbalance(100.0).unwrap(), (2.0 - 1.0) / 3.0);
+    }
+
+    #[test]
+    fn fill_base_amount() {
+        let fill = book()
+            .estimate_fill(OrderSide::Buy, OrderAmount::Base(3.0))
+            .unwrap();
+        assert!(fill.complete);
+        assert_eq!(fill.levels, 2);
+        assert_close(fill.base, 3.0);
+        assert_close(fill.quote, 101.0 + 2.0 * 102.0);
+        assert_close(fill.average_price, 305.0 / 3.0);
+        assert_close(fill.slippage_bps, (305.0 / 3.0 - 100.0) / 100.0 * BPS);
+
+        let fill = book()
+            .estimate_fill(OrderSide::Sell, OrderAmount::Base(2.0))
+            .unwrap();
+        assert!(fill.complete);
+        assert_eq!(fill.levels, 1);
+        assert_close(fill.average_price, 99.0);
+        assert_close(fill.slippage_bps, 100.0);
+    }
+
+    #[test]
+    fn fill_quote_amount() {
+        let fill = book()
+            .estimate_fill(OrderSide::Buy, OrderAmount::Quote(305.0))
+            .unwrap();
+        assert!(fill.complete);
+        assert_eq!(fill.levels, 2);
+        assert_close(fill.base, 3.0);
+        assert_close(fill.quote, 305.0);
+
+        // Partially consumes the third level.
+        let fill = book()
+            .estimate_fill(OrderSide::Sell, OrderAmount::Quote(1392.0 - 450.0))
+            .unwrap();
+        assert!(fill.complete);
+        assert_eq!(fill.levels, 3);
+        assert_close(fill.base, 10.0);
+    }
+
+    #[test]
+    fn incomplete_fill() {
+        let fill = book()
+            .estimate_fill(OrderSide::Sell, OrderAmount::Base(100.0))
+            .unwrap();
+        assert!(!fill.complete);
+        assert_eq!(fill.levels, 3);
+        assert_close(fill.base, 15.0);
+        assert_close(fill.quote, 1392.0);
+        assert_close(fill.average_price, 92.8);
+    }
+
+    #[test]
+    fn empty_side() {
+        let snapshot = PartialDepthStream {
+            last_update_id: 1,
+            bids: levels(&[("99", "2")]),
+            asks: Vec::new(),
+        };
+        let book = OrderBook::from_snapshot("BTCUSDT", &snapshot).unwrap();
+        assert_eq!(book.mid_price(), None);
+        assert_eq!(book.microprice(), None);
+        assert_eq!(
+            book.estimate_fill(OrderSide::Sell, OrderAmount::Base(1.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn unparsable_snapshot() {
+        let snapshot = PartialDepthStream {
+            last_update_id: 1,
+            bids: levels(&[("99", "2"), ("98", "")]),
+            asks: levels(&[("101", "1")]),
+        };
+        assert!(OrderBook::from_snapshot("BTCUSDT", &snapshot).is_err());
+    }
+}
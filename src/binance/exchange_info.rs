@@ -0,0 +1,331 @@
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::binance::historical::Market;
+use crate::decimal::Decimal;
+use crate::errors::Error;
+use crate::fs::parse::{string_to_decimal, string_to_f64};
+
+// File exchange info is saved to, within the directory of a market.
+const EXCHANGE_INFO_FILE_NAME: &str = "exchangeInfo.json";
+
+/// Location exchange info of `market` is saved to, e.g. `data/spot/exchangeInfo.json`.
+pub fn get_exchange_info_cache_path<P: AsRef<Path>>(root: P, market: &Market) -> PathBuf {
+    root.as_ref()
+        .join(market.to_string())
+        .join(EXCHANGE_INFO_FILE_NAME)
+}
+
+// Deserialize the response of `/api/v3/exchangeInfo`, or its futures equivalents.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExchangeInfo {
+    pub timezone: String,
+    pub server_time: i64,
+    pub symbols: Vec<SymbolInfo>,
+}
+
+impl ExchangeInfo {
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Loads exchange info saved previously, see `RestClient::cached_exchange_info`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn symbol(&self, symbol: &str) -> Option<&SymbolInfo> {
+        self.symbols
+            .iter()
+            .find(|info| info.symbol.eq_ignore_ascii_case(symbol))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SymbolStatus {
+    Trading,
+    EndOfDay,
+    Halt,
+    Break,
+    /// Statuses not listed above, e.g. those specific to futures.
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolInfo {
+    pub symbol: String,
+    // COIN-M futures name the status `contractStatus`.
+    #[serde(alias = "contractStatus")]
+    pub status: SymbolStatus,
+    pub base_asset: String,
+    pub base_asset_precision: u32,
+    pub quote_asset: String,
+    pub quote_precision: u32,
+    pub filters: Vec<SymbolFilter>,
+}
+
+impl SymbolInfo {
+    pub fn is_trading(&self) -> bool {
+        self.status == SymbolStatus::Trading
+    }
+
+    pub fn price_filter(&self) -> Option<&PriceFilter> {
+        self.filters.iter().find_map(|filter| match filter {
+            SymbolFilter::Price(filter) => Some(filter),
+            _ => None,
+        })
+    }
+
+    pub fn lot_size(&self) -> Option<&LotSizeFilter> {
+        self.filters.iter().find_map(|filter| match filter {
+            SymbolFilter::LotSize(filter) => Some(filter),
+            _ => None,
+        })
+    }
+
+    /// Minimum notional value of an order, taken from either the
+    /// `MIN_NOTIONAL` or the `NOTIONAL` filter.
+    pub fn min_notional(&self) -> Option<f64> {
+        self.filters.iter().find_map(|filter| match filter {
+            SymbolFilter::MinNotional(filter) => Some(filter.min_notional),
+            SymbolFilter::Notional(filter) => Some(filter.min_notional),
+            _ => None,
+        })
+    }
+
+    pub fn percent_price_by_side(&self) -> Option<&PercentPriceBySideFilter> {
+        self.filters.iter().find_map(|filter| match filter {
+            SymbolFilter::PercentPriceBySide(filter) => Some(filter),
+            _ => None,
+        })
+    }
+
+    /// Rounds `price` to the nearest multiple of the tick size.
+    pub fn round_price(&self, price: f64) -> f64 {
+        match self.price_filter() {
+            Some(filter) => round_to_increment(price, filter.tick_size, Rounding::Nearest),
+            None => price,
+        }
+    }
+
+    /// Rounds `quantity` down to a multiple of the step size, so that
+    /// the quantity never exceeds the quantity available.
+    pub fn round_quantity(&self, quantity: f64) -> f64 {
+        match self.lot_size() {
+            Some(filter) => round_to_increment(quantity, filter.step_size, Rounding::Down),
+            None => quantity,
+        }
+    }
+}
+
+/// Filters restricting the orders placed for a symbol, see
+/// https://developers.binance.com/docs/binance-spot-api-docs/filters
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "filterType")]
+pub enum SymbolFilter {
+    #[serde(rename = "PRICE_FILTER")]
+    Price(PriceFilter),
+    #[serde(rename = "LOT_SIZE")]
+    LotSize(LotSizeFilter),
+    #[serde(rename = "MIN_NOTIONAL")]
+    MinNotional(MinNotionalFilter),
+    #[serde(rename = "NOTIONAL")]
+    Notional(NotionalFilter),
+    #[serde(rename = "PERCENT_PRICE_BY_SIDE")]
+    PercentPriceBySide(PercentPriceBySideFilter),
+    /// Filters not listed above.
+    #[serde(other)]
+    Other,
+}
+
+// A value of zero disables the corresponding rule.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceFilter {
+    #[serde(deserialize_with = "string_to_f64")]
+    pub min_price: f64,
+    #[serde(deserialize_with = "string_to_f64")]
+    pub max_price: f64,
+    #[serde(deserialize_with = "string_to_decimal")]
+    pub tick_size: Decimal,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LotSizeFilter {
+    #[serde(deserialize_with = "string_to_f64")]
+    pub min_qty: f64,
+    #[serde(deserialize_with = "string_to_f64")]
+    pub max_qty: f64,
+    #[serde(deserialize_with = "string_to_decimal")]
+    pub step_size: Decimal,
+}
+
+// Futures name the minimum `notional` rather than `minNotional`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MinNotionalFilter {
+    #[serde(alias = "notional", deserialize_with = "string_to_f64")]
+    pub min_notional: f64,
+    pub apply_to_market: Option<bool>,
+    pub avg_price_mins: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotionalFilter {
+    #[serde(deserialize_with = "string_to_f64")]
+    pub min_notional: f64,
+    pub apply_min_to_market: bool,
+    #[serde(deserialize_with = "string_to_f64")]
+    pub max_notional: f64,
+    pub apply_max_to_market: bool,
+    pub avg_price_mins: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PercentPriceBySideFilter {
+    #[serde(deserialize_with = "string_to_f64")]
+    pub bid_multiplier_up: f64,
+    #[serde(deserialize_with = "string_to_f64")]
+    pub bid_multiplier_down: f64,
+    #[serde(deserialize_with = "string_to_f64")]
+    pub ask_multiplier_up: f64,
+    #[serde(deserialize_with = "string_to_f64")]
+    pub ask_multiplier_down: f64,
+    pub avg_price_mins: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Nearest,
+    Down,
+}
+
+/// Rounds `value` to a multiple of `increment`, e.g. a tick or step size.
+/// The result is the closest `f64` to the exact multiple, i.e. without
+/// floating point noise. An increment of zero leaves the value as is.
+pub fn round_to_increment(value: f64, increment: Decimal, rounding: Rounding) -> f64 {
+    if increment <= Decimal::ZERO {
+        return value;
+    }
+    // Works in units of the increment's last decimal, in which the
+    // increment is an integer, e.g. 5 for an increment of `0.05`.
+    let factor = 10f64.powi(increment.decimals() as i32);
+    let units = (increment.to_f64() * factor).round();
+    let steps = value * factor / units;
+    let steps = match rounding {
+        Rounding::Nearest => steps.round(),
+        // Allows for values which are a multiple, but not exactly so.
+        Rounding::Down => (steps + (steps.abs() * 1e-12).max(1e-9)).floor(),
+    };
+    steps * units / factor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    fn symbol_info(tick_size: &str, step_size: &str) -> SymbolInfo {
+        let json = format!(
+            r#"{{
+                "symbol": "ETHBTC",
+                "status": "TRADING",
+                "baseAsset": "ETH",
+                "baseAssetPrecision": 8,
+                "quoteAsset": "BTC",
+                "quotePrecision": 8,
+                "filters": [
+                    {{"filterType": "PRICE_FILTER", "minPrice": "0.00001000", "maxPrice": "922327.00000000", "tickSize": "{tick_size}"}},
+                    {{"filterType": "LOT_SIZE", "minQty": "0.00010000", "maxQty": "100000.00000000", "stepSize": "{step_size}"}},
+                    {{"filterType": "ICEBERG_PARTS", "limit": 10}}
+                ]
+            }}"#
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn round_to_small_increments() {
+        let info = symbol_info("0.00000001", "0.00000001");
+        assert_eq!(info.round_price(0.00001234), 0.00001234);
+        assert_eq!(info.round_price(0.000012345), 0.00001235);
+        assert_eq!(info.round_quantity(0.5), 0.5);
+        assert_eq!(info.round_quantity(0.123456789), 0.12345678);
+        assert_eq!(info.round_quantity(0.00000001), 0.00000001);
+    }
+
+    #[test]
+    fn round_to_cent_increments() {
+        let increment = decimal("0.01");
+        assert_eq!(
+            round_to_increment(104591.876, increment, Rounding::Nearest),
+            104591.88
+        );
+        assert_eq!(
+            round_to_increment(104591.876, increment, Rounding::Down),
+            104591.87
+        );
+        // Multiples which are not exactly representable are not rounded down.
+        assert_eq!(round_to_increment(4.35, increment, Rounding::Down), 4.35);
+        assert_eq!(
+            round_to_increment(0.1 + 0.2, increment, Rounding::Down),
+            0.3
+        );
+        assert_eq!(
+            round_to_increment(0.05, decimal("0.05"), Rounding::Down),
+            0.05
+        );
+        assert_eq!(
+            round_to_increment(0.12, decimal("0.05"), Rounding::Nearest),
+            0.1
+        );
+    }
+
+    #[test]
+    fn round_to_whole_increments() {
+        let increment = decimal("1.00000000");
+        assert_eq!(
+            round_to_increment(2.5001, increment, Rounding::Nearest),
+            3.0
+        );
+        assert_eq!(round_to_increment(2.9999, increment, Rounding::Down), 2.0);
+        assert_eq!(round_to_increment(3.0, increment, Rounding::Down), 3.0);
+        assert_eq!(round_to_increment(17.0, decimal("5"), Rounding::Down), 15.0);
+        assert_eq!(
+            round_to_increment(1.2345, Decimal::ZERO, Rounding::Down),
+            1.2345
+        );
+    }
+
+    #[test]
+    fn coin_m_contract_status() {
+        let json = r#"{
+            "symbol": "BTCUSD_PERP",
+            "pair": "BTCUSD",
+            "contractType": "PERPETUAL",
+            "contractStatus": "TRADING",
+            "baseAsset": "BTC",
+            "baseAssetPrecision": 8,
+            "quoteAsset": "USD",
+            "quotePrecision": 8,
+            "filters": [
+                {"filterType": "PRICE_FILTER", "minPrice": "1000", "maxPrice": "4520958", "tickSize": "0.1"},
+                {"filterType": "LOT_SIZE", "minQty": "1", "maxQty": "1000000", "stepSize": "1"}
+            ]
+        }"#;
+        let info: SymbolInfo = serde_json::from_str(json).unwrap();
+        assert!(info.is_trading());
+        assert_eq!(info.round_price(104591.87), 104591.9);
+        assert_eq!(info.round_quantity(12.7), 12.0);
+    }
+}
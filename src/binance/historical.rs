@@ -1,70 +1,1610 @@
-use chrono::NaiveDate;
-use std::path::Path;
+use chrono::{Datelike, Days, Months, NaiveDate, Utc};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinSet;
 
+use crate::binance::catalog::{Catalog, CatalogFilter};
+use crate::binance::rest::{RestClient, RestConfig};
 use crate::errors::Error;
+use crate::fs::cache::read_klines_from_zip_file_cached;
+use crate::fs::checksum::{parse_checksum, sha256_file_digest, verify_digest};
+use crate::fs::read::{
+    identify_files_recursively, read_delimited_from_zip_file, read_files_in_parallel,
+};
 use crate::fs::write::async_write_safely;
+use crate::models::{
+    AggTrade, FromDelimitedString, FundingRate, HistoricalKlineEvent, PriceKline, Trade,
+};
 
 const BASE: &str = "https://data.binance.vision/data";
 
-fn get_remove_file_name(symbol: &str, interval: &str, date: &NaiveDate) -> String {
-    format!("{symbol}-{interval}-{}.zip", date.format("%Y-%m"))
+// Default directory archives are saved to.
+const DATA_ROOT: &str = "data";
+
+// REST API of each market.
+const SPOT_REST_END_POINT: &str = "https://api.binance.com/api/v3";
+const USD_M_FUTURES_REST_END_POINT: &str = "https://fapi.binance.com/fapi/v1";
+const COIN_M_FUTURES_REST_END_POINT: &str = "https://dapi.binance.com/dapi/v1";
+
+/// Markets for which archives are published.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Market {
+    Spot,
+    /// USD-M (USDT/USDC margined) futures.
+    UsdMFutures,
+    /// COIN-M (coin margined) futures.
+    CoinMFutures,
+}
+
+impl std::fmt::Display for Market {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Spot => "spot",
+            Self::UsdMFutures => "futures/um",
+            Self::CoinMFutures => "futures/cm",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl Market {
+    pub const ALL: [Market; 3] = [Self::Spot, Self::UsdMFutures, Self::CoinMFutures];
+
+    /// Base URL of the market's REST API, e.g. `https://api.binance.com/api/v3`.
+    pub fn rest_end_point(&self) -> &'static str {
+        match self {
+            Self::Spot => SPOT_REST_END_POINT,
+            Self::UsdMFutures => USD_M_FUTURES_REST_END_POINT,
+            Self::CoinMFutures => COIN_M_FUTURES_REST_END_POINT,
+        }
+    }
+}
+
+// Every archive is accompanied by a file, with this extension appended
+// to the archive name, containing the SHA-256 digest of the archive.
+const CHECKSUM_EXTENSION: &str = "CHECKSUM";
+
+/// Publication frequency of the archives hosted on data.binance.vision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ArchiveFrequency {
+    Daily,
+    Monthly,
+}
+
+impl std::fmt::Display for ArchiveFrequency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Daily => "daily",
+            Self::Monthly => "monthly",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl ArchiveFrequency {
+    /// Date format used in archive file names, i.e. `YYYY-MM-DD` for
+    /// daily and `YYYY-MM` for monthly archives.
+    pub(crate) fn date_format(&self) -> &'static str {
+        match self {
+            Self::Daily => "%Y-%m-%d",
+            Self::Monthly => "%Y-%m",
+        }
+    }
+
+    /// Last day covered by the archive starting at `date`.
+    pub(crate) fn period_end(&self, date: &NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Self::Daily => Some(*date),
+            Self::Monthly => date
+                .with_day(1)?
+                .checked_add_months(Months::new(1))?
+                .pred_opt(),
+        }
+    }
+}
+
+/// Generates the first day of every month between `start` and `end` (inclusive).
+pub fn generate_monthly_date_range(
+    start: NaiveDate,
+    end: NaiveDate,
+) -> impl Iterator<Item = NaiveDate> {
+    let mut current = start.with_day(1);
+    std::iter::from_fn(move || {
+        let previous = current.filter(|date| date <= &end)?;
+        current = previous.checked_add_months(Months::new(1));
+        Some(previous)
+    })
+}
+
+/// Generates every day between `start` and `end` (inclusive).
+pub fn generate_daily_date_range(
+    start: NaiveDate,
+    end: NaiveDate,
+) -> impl Iterator<Item = NaiveDate> {
+    let mut current = Some(start);
+    std::iter::from_fn(move || {
+        let previous = current.filter(|date| date <= &end)?;
+        current = previous.checked_add_days(Days::new(1));
+        Some(previous)
+    })
+}
+
+/// Splits the range `start..=end` into the archives required to cover it.
+/// Completed months are covered by monthly archives, while the month
+/// containing `today` is covered by daily archives up to (and including)
+/// yesterday, since neither is published before the period has ended.
+pub fn plan_archives(
+    start: NaiveDate,
+    end: NaiveDate,
+    today: NaiveDate,
+) -> Vec<(ArchiveFrequency, NaiveDate)> {
+    let mut plan = Vec::new();
+    let Some(current_month) = today.with_day(1) else {
+        return plan;
+    };
+
+    if let Some(last_completed_month) = current_month.pred_opt() {
+        let monthly_end = end.min(last_completed_month);
+        plan.extend(
+            generate_monthly_date_range(start, monthly_end)
+                .map(|date| (ArchiveFrequency::Monthly, date)),
+        );
+    }
+
+    if let Some(yesterday) = today.pred_opt() {
+        let daily_start = start.max(current_month);
+        let daily_end = end.min(yesterday);
+        plan.extend(
+            generate_daily_date_range(daily_start, daily_end)
+                .map(|date| (ArchiveFrequency::Daily, date)),
+        );
+    }
+
+    plan
 }
 
-fn get_remote_file_path(frequency: &str, symbol: &str, interval: &str) -> String {
-    format!("{BASE}/spot/{frequency}/klines/{symbol}/{interval}")
+/// Replaces the monthly archives of `plan` for which `unavailable` returns
+/// `true` by the daily archives of that month within `start..=end`. Monthly
+/// archives are published a few days after the month has ended, in the
+/// meantime the last completed month is only covered by daily archives.
+pub fn fall_back_to_daily_archives<F>(
+    plan: Vec<(ArchiveFrequency, NaiveDate)>,
+    start: NaiveDate,
+    end: NaiveDate,
+    unavailable: F,
+) -> Vec<(ArchiveFrequency, NaiveDate)>
+where
+    F: Fn(&NaiveDate) -> bool,
+{
+    let mut monthly = Vec::new();
+    let mut daily = Vec::new();
+    for (frequency, date) in plan {
+        match frequency {
+            ArchiveFrequency::Monthly if unavailable(&date) => {
+                let month_end = ArchiveFrequency::Monthly
+                    .period_end(&date)
+                    .unwrap_or(date)
+                    .min(end);
+                daily.extend(generate_daily_date_range(date.max(start), month_end));
+            }
+            ArchiveFrequency::Monthly => monthly.push((frequency, date)),
+            ArchiveFrequency::Daily => daily.push(date),
+        }
+    }
+    daily.sort();
+    daily.dedup();
+    monthly.extend(
+        daily
+            .into_iter()
+            .map(|date| (ArchiveFrequency::Daily, date)),
+    );
+    monthly
 }
 
-fn get_local_file_name(symbol: &str, interval: &str, date: &NaiveDate) -> String {
-    get_remove_file_name(symbol, interval, date)
+/// First day of the month preceding the month containing `today`.
+pub fn last_completed_month(today: NaiveDate) -> Option<NaiveDate> {
+    today
+        .with_day(1)
+        .and_then(|date| date.checked_sub_months(Months::new(1)))
+}
+
+/// Datasets published on data.binance.vision.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Dataset {
+    /// Klines of the specified interval, e.g. `1h`.
+    Klines(String),
+    AggTrades,
+    Trades,
+    /// Futures only, mark price klines of the specified interval.
+    MarkPriceKlines(String),
+    /// Futures only, index price klines of the specified interval.
+    IndexPriceKlines(String),
+    /// Futures only, premium index klines of the specified interval.
+    PremiumIndexKlines(String),
+    /// Futures only, only published as monthly archives.
+    FundingRate,
+}
+
+impl std::fmt::Display for Dataset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Klines(_) => "klines",
+            Self::AggTrades => "aggTrades",
+            Self::Trades => "trades",
+            Self::MarkPriceKlines(_) => "markPriceKlines",
+            Self::IndexPriceKlines(_) => "indexPriceKlines",
+            Self::PremiumIndexKlines(_) => "premiumIndexKlines",
+            Self::FundingRate => "fundingRate",
+        };
+        write!(f, "{s}")
+    }
 }
 
-fn get_local_file_path(frequency: &str, symbol: &str, interval: &str) -> String {
-    format!("data/spot/{frequency}/klines/{symbol}/{interval}")
+impl Dataset {
+    /// Dataset from its directory name, e.g. `klines`, and interval,
+    /// which is required for (and only for) kline datasets.
+    pub(crate) fn from_parts(name: &str, interval: Option<&str>) -> Option<Self> {
+        let dataset = match (name, interval) {
+            ("klines", Some(interval)) => Self::Klines(interval.to_string()),
+            ("markPriceKlines", Some(interval)) => Self::MarkPriceKlines(interval.to_string()),
+            ("indexPriceKlines", Some(interval)) => Self::IndexPriceKlines(interval.to_string()),
+            ("premiumIndexKlines", Some(interval)) => {
+                Self::PremiumIndexKlines(interval.to_string())
+            }
+            ("aggTrades", None) => Self::AggTrades,
+            ("trades", None) => Self::Trades,
+            ("fundingRate", None) => Self::FundingRate,
+            _ => return None,
+        };
+        Some(dataset)
+    }
+
+    pub(crate) fn interval(&self) -> Option<&str> {
+        match self {
+            Self::Klines(interval)
+            | Self::MarkPriceKlines(interval)
+            | Self::IndexPriceKlines(interval)
+            | Self::PremiumIndexKlines(interval) => Some(interval),
+            Self::AggTrades | Self::Trades | Self::FundingRate => None,
+        }
+    }
+
+    /// Directory of the dataset relative to the market directory,
+    /// e.g. `klines/BTCUSDT/1h` or `aggTrades/BTCUSDT`.
+    pub(crate) fn directory(&self, symbol: &str) -> String {
+        match self.interval() {
+            Some(interval) => format!("{self}/{symbol}/{interval}"),
+            None => format!("{self}/{symbol}"),
+        }
+    }
+
+    /// Archive name excluding the period, e.g. `BTCUSDT-1h` or `BTCUSDT-aggTrades`.
+    pub(crate) fn file_prefix(&self, symbol: &str) -> String {
+        match self.interval() {
+            Some(interval) => format!("{symbol}-{interval}"),
+            None => format!("{symbol}-{self}"),
+        }
+    }
 }
 
-fn is_saved(frequency: &str, symbol: &str, interval: &str, date: &NaiveDate) -> bool {
-    let local_file_path = get_local_file_path(frequency, symbol, interval);
-    let local_file_name = get_local_file_name(symbol, interval, date);
-    let local_full_path = Path::new(&local_file_path).join(&local_file_name);
-    local_full_path.exists() && local_full_path.is_file()
+fn get_remove_file_name(
+    frequency: &ArchiveFrequency,
+    symbol: &str,
+    dataset: &Dataset,
+    date: &NaiveDate,
+) -> String {
+    format!(
+        "{}-{}.zip",
+        dataset.file_prefix(symbol),
+        date.format(frequency.date_format())
+    )
 }
 
-async fn retrieve_historical_data(
-    client: &reqwest::Client,
-    frequency: &str,
+fn get_local_file_name(
+    frequency: &ArchiveFrequency,
     symbol: &str,
-    interval: &str,
+    dataset: &Dataset,
     date: &NaiveDate,
-) -> Result<Vec<u8>, Error> {
-    let path = get_remote_file_path(frequency, symbol, interval);
-    let file = get_remove_file_name(symbol, interval, date);
-    let url = format!("{}/{}", path, file);
-    let response = client.get(url).send().await?;
-    let text = response.bytes().await?.to_vec();
-    Ok(text)
-}
-
-pub async fn retrieve_and_save_historical_data_range<I>(
-    dates: I,
-    frequency: &str,
+) -> String {
+    get_remove_file_name(frequency, symbol, dataset, date)
+}
+
+/// Appends the checksum extension to an archive file name or path.
+fn get_checksum_file_name(file: &str) -> String {
+    format!("{file}.{CHECKSUM_EXTENSION}")
+}
+
+/// Configuration of a `HistoricalClient`.
+#[derive(Debug, Clone)]
+pub struct HistoricalConfig {
+    /// Location archives are downloaded from, e.g. a mirror of data.binance.vision.
+    pub base_url: String,
+    /// Directory archives are saved to.
+    pub local_root: PathBuf,
+    /// Timeout applied to every request.
+    pub timeout: Option<Duration>,
+    /// Proxy all requests are routed through.
+    pub proxy: Option<String>,
+    pub user_agent: Option<String>,
+    /// Whether parsed klines are cached next to their archives, which
+    /// avoids parsing the archives again on subsequent loads.
+    pub cache: bool,
+    /// REST APIs (one per market) used to look up the listing date of a
+    /// symbol, which avoids requesting archives of periods before the symbol
+    /// was listed. The lookup is skipped for markets without a REST API,
+    /// hence it is disabled by an empty `Vec`, e.g. for tests and mirrors.
+    pub listing_dates: Vec<RestConfig>,
+}
+
+impl Default for HistoricalConfig {
+    fn default() -> Self {
+        Self {
+            base_url: BASE.to_string(),
+            local_root: PathBuf::from(DATA_ROOT),
+            timeout: Some(Duration::from_secs(60)),
+            proxy: None,
+            user_agent: None,
+            cache: true,
+            listing_dates: Market::ALL
+                .into_iter()
+                .map(|market| RestConfig {
+                    market,
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Downloads archives from, and loads archives previously downloaded
+/// from, data.binance.vision (or a mirror thereof).
+#[derive(Debug, Clone)]
+pub struct HistoricalClient {
+    base_url: String,
+    local_root: PathBuf,
+    client: reqwest::Client,
+    cache: bool,
+    listing_dates: Vec<RestClient>,
+}
+
+impl HistoricalClient {
+    pub fn new(config: HistoricalConfig) -> Result<Self, Error> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = config.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(proxy) = &config.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+        if let Some(user_agent) = &config.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+
+        Ok(Self {
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            local_root: config.local_root,
+            client: builder.build()?,
+            cache: config.cache,
+            listing_dates: config
+                .listing_dates
+                .into_iter()
+                .map(RestClient::new)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub fn local_root(&self) -> &Path {
+        &self.local_root
+    }
+
+    fn get_remote_file_path(
+        &self,
+        frequency: &ArchiveFrequency,
+        market: &Market,
+        symbol: &str,
+        dataset: &Dataset,
+    ) -> String {
+        format!(
+            "{}/{market}/{frequency}/{}",
+            self.base_url,
+            dataset.directory(symbol)
+        )
+    }
+
+    pub fn get_local_file_path(
+        &self,
+        frequency: &ArchiveFrequency,
+        market: &Market,
+        symbol: &str,
+        dataset: &Dataset,
+    ) -> PathBuf {
+        self.local_root.join(format!(
+            "{market}/{frequency}/{}",
+            dataset.directory(symbol)
+        ))
+    }
+
+    fn get_local_full_path(
+        &self,
+        frequency: &ArchiveFrequency,
+        market: &Market,
+        symbol: &str,
+        dataset: &Dataset,
+        date: &NaiveDate,
+    ) -> PathBuf {
+        let local_file_path = self.get_local_file_path(frequency, market, symbol, dataset);
+        let local_file_name = get_local_file_name(frequency, symbol, dataset, date);
+        local_file_path.join(local_file_name)
+    }
+
+    /// Whether the archive is saved locally.
+    pub fn is_saved(
+        &self,
+        frequency: &ArchiveFrequency,
+        market: &Market,
+        symbol: &str,
+        dataset: &Dataset,
+        date: &NaiveDate,
+    ) -> bool {
+        let local_full_path = self.get_local_full_path(frequency, market, symbol, dataset, date);
+        local_full_path.exists() && local_full_path.is_file()
+    }
+
+    /// Sends a GET request, distinguishing archives that are not (yet)
+    /// published from other unsuccessful responses, so that error bodies
+    /// are never mistaken for archive content.
+    async fn get_archive_response(&self, url: &str) -> Result<reqwest::Response, Error> {
+        let response = self.client.get(url).send().await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(Error::ArchiveNotAvailable(url.to_string()));
+        }
+        Ok(response.error_for_status()?)
+    }
+
+    /// Retrieves an archive together with its published checksum, and ensures
+    /// the archive matches the checksum before returning both.
+    async fn retrieve_historical_data(
+        &self,
+        frequency: &ArchiveFrequency,
+        market: &Market,
+        symbol: &str,
+        dataset: &Dataset,
+        date: &NaiveDate,
+    ) -> Result<(Vec<u8>, String), Error> {
+        let path = self.get_remote_file_path(frequency, market, symbol, dataset);
+        let file = get_remove_file_name(frequency, symbol, dataset, date);
+        let url = format!("{}/{}", path, file);
+
+        let checksum_response = self
+            .get_archive_response(&get_checksum_file_name(&url))
+            .await?;
+        let checksum = checksum_response.text().await?;
+        let digest = parse_checksum(&checksum)
+            .ok_or_else(|| Error::Checksum(format!("Invalid checksum file for {file}")))?;
+
+        let response = self.get_archive_response(&url).await?;
+        let text = response.bytes().await?.to_vec();
+        verify_digest(&text, &digest, &file)?;
+        Ok((text, checksum))
+    }
+
+    /// Retrieves the date of the first kline of a symbol, i.e. the date the
+    /// symbol was listed. No archives exist for periods before this date.
+    /// Returns `None` when no REST API of the market is configured, see
+    /// `HistoricalConfig::listing_dates`.
+    pub async fn retrieve_listing_date(
+        &self,
+        market: &Market,
+        symbol: &str,
+    ) -> Result<Option<NaiveDate>, Error> {
+        match self
+            .listing_dates
+            .iter()
+            .find(|rest| &rest.market() == market)
+        {
+            Some(rest) => rest.listing_date(symbol).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Retrieves and saves a single archive, retrying transient failures
+    /// with exponential backoff. Returns the number of bytes saved.
+    async fn retrieve_and_save_historical_data(
+        self,
+        frequency: ArchiveFrequency,
+        market: Market,
+        symbol: String,
+        dataset: Dataset,
+        date: NaiveDate,
+        options: DownloadOptions,
+    ) -> Result<usize, Error> {
+        let local_path = self.get_local_file_path(&frequency, &market, &symbol, &dataset);
+        let local_file = get_local_file_name(&frequency, &symbol, &dataset, &date);
+        options.report(DownloadProgress::Started(local_file.clone()));
+
+        let mut attempt = 0;
+        let (file, checksum) = loop {
+            match self
+                .retrieve_historical_data(&frequency, &market, &symbol, &dataset, &date)
+                .await
+            {
+                Ok(retrieved) => break retrieved,
+                Err(e) if attempt < options.max_retries && is_transient(&e) => {
+                    attempt += 1;
+                    let delay = options.backoff(attempt);
+                    options.report(DownloadProgress::Retrying {
+                        file: local_file.clone(),
+                        attempt,
+                        delay,
+                        reason: e.to_string(),
+                    });
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => return Err(e),
+            }
+        };
+
+        let path = local_path.join(&local_file);
+        let checksum_path = local_path.join(get_checksum_file_name(&local_file));
+        // The checksum is written first, so that an archive is never
+        // stored without the digest required to verify it.
+        async_write_safely(checksum_path, &checksum).await?;
+        async_write_safely(path, &file).await?;
+        Ok(file.len())
+    }
+
+    /// Downloads the kline archives of `interval` for the specified periods.
+    pub async fn retrieve_and_save_historical_data_range<I>(
+        &self,
+        dates: I,
+        frequency: &ArchiveFrequency,
+        market: &Market,
+        symbol: &str,
+        interval: &str,
+        options: &DownloadOptions,
+    ) -> Result<DownloadReport, Error>
+    where
+        I: IntoIterator<Item = NaiveDate>,
+    {
+        let dataset = Dataset::Klines(interval.to_string());
+        self.retrieve_and_save_dataset_range(dates, frequency, market, symbol, &dataset, options)
+            .await
+    }
+
+    /// Downloads the archives of any dataset for the specified periods.
+    pub async fn retrieve_and_save_dataset_range<I>(
+        &self,
+        dates: I,
+        frequency: &ArchiveFrequency,
+        market: &Market,
+        symbol: &str,
+        dataset: &Dataset,
+        options: &DownloadOptions,
+    ) -> Result<DownloadReport, Error>
+    where
+        I: IntoIterator<Item = NaiveDate>,
+    {
+        let mut report = DownloadReport::default();
+
+        // The listing date is only used to avoid redundant requests, hence
+        // failing to retrieve it (e.g. for delisted symbols) is not an error.
+        let listing_date = self
+            .retrieve_listing_date(market, symbol)
+            .await
+            .ok()
+            .flatten();
+
+        let mut downloads = JoinSet::new();
+        for date in dates {
+            let period_end = frequency.period_end(&date);
+            if let (Some(listing_date), Some(period_end)) = (listing_date, period_end)
+                && period_end < listing_date
+            {
+                report.before_listing.push(date);
+                continue;
+            }
+
+            // Data is only downloaded if not yet available.
+            if !options.overwrite && self.is_saved(frequency, market, symbol, dataset, &date) {
+                report.existing.push(date);
+                continue;
+            }
+
+            // Wait for a download to finish before exceeding the concurrency limit.
+            if downloads.len() >= options.concurrency.max(1)
+                && let Some(result) = downloads.join_next().await
+            {
+                record_download(result?, &mut report, frequency, symbol, dataset, options);
+            }
+
+            let download = self.clone().retrieve_and_save_historical_data(
+                *frequency,
+                *market,
+                symbol.to_string(),
+                dataset.clone(),
+                date,
+                options.clone(),
+            );
+            downloads.spawn(async move { (date, download.await) });
+        }
+
+        while let Some(result) = downloads.join_next().await {
+            record_download(result?, &mut report, frequency, symbol, dataset, options);
+        }
+
+        report.downloaded.sort();
+        report.missing.sort();
+        report.failed.sort_by_key(|(date, _)| *date);
+        Ok(report)
+    }
+
+    /// Downloads the archives covering `start..=end`, using monthly archives
+    /// for completed months and daily archives for the current month. The
+    /// last completed month falls back to daily archives while its monthly
+    /// archive is not yet published.
+    pub async fn retrieve_and_save_historical_data_up_to_date(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+        market: &Market,
+        symbol: &str,
+        dataset: &Dataset,
+        options: &DownloadOptions,
+    ) -> Result<Vec<(ArchiveFrequency, DownloadReport)>, Error> {
+        let today = Utc::now().date_naive();
+        let plan = plan_archives(start, end, today);
+        let monthly = plan
+            .iter()
+            .filter(|(f, _)| f == &ArchiveFrequency::Monthly)
+            .map(|(_, date)| *date);
+        let monthly_report = self
+            .retrieve_and_save_dataset_range(
+                monthly,
+                &ArchiveFrequency::Monthly,
+                market,
+                symbol,
+                dataset,
+                options,
+            )
+            .await?;
+
+        let last_completed_month = last_completed_month(today);
+        let plan = fall_back_to_daily_archives(plan, start, end, |month| {
+            Some(*month) == last_completed_month && monthly_report.missing.contains(month)
+        });
+        let mut reports = vec![(ArchiveFrequency::Monthly, monthly_report)];
+
+        // Funding rates are not published as daily archives.
+        if dataset != &Dataset::FundingRate {
+            let daily = plan
+                .iter()
+                .filter(|(f, _)| f == &ArchiveFrequency::Daily)
+                .map(|(_, date)| *date);
+            let daily_report = self
+                .retrieve_and_save_dataset_range(
+                    daily,
+                    &ArchiveFrequency::Daily,
+                    market,
+                    symbol,
+                    dataset,
+                    options,
+                )
+                .await?;
+            reports.push((ArchiveFrequency::Daily, daily_report));
+        }
+        Ok(reports)
+    }
+
+    /// Downloads the archives of `dataset` which are not yet stored locally,
+    /// from `since` (or, if `None`, the first day stored locally or else the
+    /// listing date) up to the latest published period. Periods covered
+    /// locally, by either monthly or daily archives, are not requested again.
+    /// When the monthly archive of the last completed month is not yet
+    /// published, its daily archives are downloaded instead.
+    pub async fn sync(
+        &self,
+        market: &Market,
+        symbol: &str,
+        dataset: &Dataset,
+        since: Option<NaiveDate>,
+        options: &DownloadOptions,
+    ) -> Result<SyncReport, Error> {
+        let catalog = self.catalog()?;
+        self.sync_with_catalog(&catalog, market, symbol, dataset, since, options)
+            .await
+    }
+
+    /// Synchronises every dataset stored locally, see `sync`.
+    pub async fn sync_local(&self, options: &DownloadOptions) -> Result<Vec<SyncReport>, Error> {
+        let catalog = self.catalog()?;
+        let mut reports = Vec::new();
+        for coverage in catalog.coverage() {
+            let report = self
+                .sync_with_catalog(
+                    &catalog,
+                    &coverage.market,
+                    &coverage.symbol,
+                    &coverage.dataset,
+                    None,
+                    options,
+                )
+                .await?;
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+
+    async fn sync_with_catalog(
+        &self,
+        catalog: &Catalog,
+        market: &Market,
+        symbol: &str,
+        dataset: &Dataset,
+        since: Option<NaiveDate>,
+        options: &DownloadOptions,
+    ) -> Result<SyncReport, Error> {
+        let covered = catalog.covered_days(&CatalogFilter {
+            market: Some(*market),
+            symbol: Some(symbol.to_string()),
+            dataset: Some(dataset.clone()),
+            ..Default::default()
+        });
+
+        let start = match since.or(covered.first().copied()) {
+            Some(start) => start,
+            None => self
+                .retrieve_listing_date(market, symbol)
+                .await?
+                .ok_or_else(|| {
+                    Error::Other(format!(
+                        "No listing date found for {symbol}, specify the date to sync from"
+                    ))
+                })?,
+        };
+
+        let today = Utc::now().date_naive();
+        let is_covered = |frequency: &ArchiveFrequency, date: &NaiveDate| {
+            let end = frequency.period_end(date).unwrap_or(*date);
+            generate_daily_date_range(*date, end).all(|day| covered.contains(&day))
+        };
+        let plan: Vec<(ArchiveFrequency, NaiveDate)> = plan_archives(start, today, today)
+            .into_iter()
+            .filter(|(frequency, date)| !is_covered(frequency, date))
+            .collect();
+
+        let mut report = SyncReport {
+            market: *market,
+            symbol: symbol.to_string(),
+            dataset: dataset.clone(),
+            monthly: DownloadReport::default(),
+            daily: DownloadReport::default(),
+            unpublished: Vec::new(),
+        };
+
+        let monthly = plan
+            .iter()
+            .filter(|(f, _)| f == &ArchiveFrequency::Monthly)
+            .map(|(_, date)| *date);
+        report.monthly = self
+            .retrieve_and_save_dataset_range(
+                monthly,
+                &ArchiveFrequency::Monthly,
+                market,
+                symbol,
+                dataset,
+                options,
+            )
+            .await?;
+
+        let last_completed_month = last_completed_month(today);
+        let unpublished = |month: &NaiveDate| {
+            Some(*month) == last_completed_month && report.monthly.missing.contains(month)
+        };
+        if let Some(month) = last_completed_month
+            && unpublished(&month)
+        {
+            report.unpublished.push((ArchiveFrequency::Monthly, month));
+        }
+        let daily: Vec<NaiveDate> = fall_back_to_daily_archives(plan, start, today, unpublished)
+            .into_iter()
+            .filter(|(f, date)| f == &ArchiveFrequency::Daily && !covered.contains(date))
+            .map(|(_, date)| date)
+            .collect();
+
+        // Funding rates are not published as daily archives.
+        if dataset != &Dataset::FundingRate {
+            report.daily = self
+                .retrieve_and_save_dataset_range(
+                    daily,
+                    &ArchiveFrequency::Daily,
+                    market,
+                    symbol,
+                    dataset,
+                    options,
+                )
+                .await?;
+        }
+
+        // Daily archives are published the day after, hence missing
+        // archives of the most recent days are not yet published.
+        if let Some(month) = last_completed_month {
+            report.unpublished.extend(
+                report
+                    .daily
+                    .missing
+                    .iter()
+                    .filter(|date| **date >= month)
+                    .map(|date| (ArchiveFrequency::Daily, *date)),
+            );
+        }
+        Ok(report)
+    }
+
+    /// Paths of the locally saved archives of `dataset` for the specified periods.
+    fn get_saved_archive_paths<I>(
+        &self,
+        dates: I,
+        frequency: &ArchiveFrequency,
+        market: &Market,
+        symbol: &str,
+        dataset: &Dataset,
+    ) -> Vec<PathBuf>
+    where
+        I: IntoIterator<Item = NaiveDate>,
+    {
+        dates
+            .into_iter()
+            .map(|date| self.get_local_full_path(frequency, market, symbol, dataset, &date))
+            .filter(|path| path.is_file())
+            .collect()
+    }
+
+    /// Parses every locally saved archive of `dataset` for the specified
+    /// periods, in parallel. Periods without a saved archive are skipped.
+    async fn load_dataset<I, T>(
+        &self,
+        dates: I,
+        frequency: &ArchiveFrequency,
+        market: &Market,
+        symbol: &str,
+        dataset: &Dataset,
+    ) -> Result<Vec<T>, Error>
+    where
+        I: IntoIterator<Item = NaiveDate>,
+        T: for<'a> FromDelimitedString<&'a str> + Send + 'static,
+    {
+        let paths = self.get_saved_archive_paths(dates, frequency, market, symbol, dataset);
+        read_files_in_parallel(paths, |path| read_delimited_from_zip_file(path)).await
+    }
+
+    /// Loads the locally saved aggregate trades for the specified periods.
+    pub async fn load_agg_trades<I>(
+        &self,
+        dates: I,
+        frequency: &ArchiveFrequency,
+        market: &Market,
+        symbol: &str,
+        order: TradeOrder,
+    ) -> Result<Vec<AggTrade>, Error>
+    where
+        I: IntoIterator<Item = NaiveDate>,
+    {
+        let mut trades: Vec<AggTrade> = self
+            .load_dataset(dates, frequency, market, symbol, &Dataset::AggTrades)
+            .await?;
+        match order {
+            TradeOrder::Id => trades.sort_by_key(|trade| trade.a),
+            TradeOrder::Time => trades.sort_by_key(|trade| (trade.T, trade.a)),
+        }
+        Ok(trades)
+    }
+
+    /// Loads the locally saved trades for the specified periods.
+    pub async fn load_trades<I>(
+        &self,
+        dates: I,
+        frequency: &ArchiveFrequency,
+        market: &Market,
+        symbol: &str,
+        order: TradeOrder,
+    ) -> Result<Vec<Trade>, Error>
+    where
+        I: IntoIterator<Item = NaiveDate>,
+    {
+        let mut trades: Vec<Trade> = self
+            .load_dataset(dates, frequency, market, symbol, &Dataset::Trades)
+            .await?;
+        match order {
+            TradeOrder::Id => trades.sort_by_key(|trade| trade.t),
+            TradeOrder::Time => trades.sort_by_key(|trade| (trade.T, trade.t)),
+        }
+        Ok(trades)
+    }
+
+    /// Loads the locally saved klines of `interval` for the specified
+    /// periods, sorted by kline start time.
+    pub async fn load_klines<I>(
+        &self,
+        dates: I,
+        frequency: &ArchiveFrequency,
+        market: &Market,
+        symbol: &str,
+        interval: &str,
+    ) -> Result<Vec<HistoricalKlineEvent>, Error>
+    where
+        I: IntoIterator<Item = NaiveDate>,
+    {
+        let dataset = Dataset::Klines(interval.to_string());
+        let mut klines: Vec<HistoricalKlineEvent> = if self.cache {
+            let paths = self.get_saved_archive_paths(dates, frequency, market, symbol, &dataset);
+            read_files_in_parallel(paths, |path| read_klines_from_zip_file_cached(path)).await?
+        } else {
+            self.load_dataset(dates, frequency, market, symbol, &dataset)
+                .await?
+        };
+        klines.sort();
+        Ok(klines)
+    }
+
+    /// Loads the locally saved mark price, index price or premium index
+    /// klines for the specified periods, sorted by kline start time.
+    pub async fn load_price_klines<I>(
+        &self,
+        dates: I,
+        frequency: &ArchiveFrequency,
+        market: &Market,
+        symbol: &str,
+        dataset: &Dataset,
+    ) -> Result<Vec<PriceKline>, Error>
+    where
+        I: IntoIterator<Item = NaiveDate>,
+    {
+        match dataset {
+            Dataset::MarkPriceKlines(_)
+            | Dataset::IndexPriceKlines(_)
+            | Dataset::PremiumIndexKlines(_) => {}
+            _ => return Err(format!("{dataset} does not contain price klines").into()),
+        }
+        let mut klines: Vec<PriceKline> = self
+            .load_dataset(dates, frequency, market, symbol, dataset)
+            .await?;
+        klines.sort_by_key(|kline| kline.t);
+        Ok(klines)
+    }
+
+    /// Loads the locally saved funding rates for the specified
+    /// months, sorted by funding calculation time.
+    pub async fn load_funding_rates<I>(
+        &self,
+        dates: I,
+        market: &Market,
+        symbol: &str,
+    ) -> Result<Vec<FundingRate>, Error>
+    where
+        I: IntoIterator<Item = NaiveDate>,
+    {
+        let frequency = ArchiveFrequency::Monthly;
+        let mut rates: Vec<FundingRate> = self
+            .load_dataset(dates, &frequency, market, symbol, &Dataset::FundingRate)
+            .await?;
+        rates.sort_by_key(|rate| rate.T);
+        Ok(rates)
+    }
+
+    /// Verifies every archive stored under the local root, see `verify_local_archives`.
+    pub fn verify_local_archives<Q: AsRef<Path>>(
+        &self,
+        quarantine: Option<Q>,
+    ) -> Result<VerificationReport, Error> {
+        verify_local_archives(&self.local_root, quarantine)
+    }
+
+    /// Catalogs every archive stored under the local root, see `Catalog`.
+    pub fn catalog(&self) -> Result<Catalog, Error> {
+        Catalog::scan(&self.local_root)
+    }
+}
+
+/// Controls how a range of archives is downloaded.
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    /// Maximum number of archives downloaded simultaneously.
+    pub concurrency: usize,
+    /// Number of times a failed download is retried, when the failure is
+    /// considered transient (i.e. server errors, timeouts and checksum
+    /// mismatches caused by truncated transfers).
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every subsequent retry.
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay between retries.
+    pub max_backoff: Duration,
+    /// Receives per-file progress, if specified.
+    pub progress: Option<UnboundedSender<DownloadProgress>>,
+    /// Whether archives already saved locally are downloaded again. A saved
+    /// archive is only replaced once the new one matches its checksum.
+    pub overwrite: bool,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 8,
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            progress: None,
+            overwrite: false,
+        }
+    }
+}
+
+impl DownloadOptions {
+    /// Delay before the specified retry (starting at 1).
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
+
+    fn report(&self, progress: DownloadProgress) {
+        if let Some(sender) = &self.progress {
+            // Progress is informative only, a dropped receiver should
+            // not interrupt the download.
+            let _ = sender.send(progress);
+        }
+    }
+}
+
+/// Progress of an individual archive download.
+#[derive(Debug, Clone)]
+pub enum DownloadProgress {
+    Started(String),
+    Retrying {
+        file: String,
+        attempt: u32,
+        delay: Duration,
+        reason: String,
+    },
+    Completed {
+        file: String,
+        bytes: usize,
+    },
+    NotAvailable(String),
+    Failed {
+        file: String,
+        reason: String,
+    },
+}
+
+impl std::fmt::Display for DownloadProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Started(file) => write!(f, "{file}: started"),
+            Self::Retrying {
+                file,
+                attempt,
+                delay,
+                reason,
+            } => write!(
+                f,
+                "{file}: retry {attempt} in {}ms ({reason})",
+                delay.as_millis()
+            ),
+            Self::Completed { file, bytes } => write!(f, "{file}: completed ({bytes} bytes)"),
+            Self::NotAvailable(file) => write!(f, "{file}: not available"),
+            Self::Failed { file, reason } => write!(f, "{file}: failed ({reason})"),
+        }
+    }
+}
+
+/// Summary of a range download.
+#[derive(Debug, Default)]
+pub struct DownloadReport {
+    /// Periods downloaded during this run.
+    pub downloaded: Vec<NaiveDate>,
+    /// Periods already available locally.
+    pub existing: Vec<NaiveDate>,
+    /// Periods ending before the symbol was listed.
+    pub before_listing: Vec<NaiveDate>,
+    /// Periods for which no archive is published.
+    pub missing: Vec<NaiveDate>,
+    /// Periods that could not be downloaded, after retrying.
+    pub failed: Vec<(NaiveDate, Error)>,
+}
+
+impl DownloadReport {
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.failed.is_empty()
+    }
+}
+
+/// Outcome of synchronising a dataset, see `HistoricalClient::sync`.
+#[derive(Debug)]
+pub struct SyncReport {
+    pub market: Market,
+    pub symbol: String,
+    pub dataset: Dataset,
+    pub monthly: DownloadReport,
+    pub daily: DownloadReport,
+    /// Archives of the most recent periods which are not published yet.
+    pub unpublished: Vec<(ArchiveFrequency, NaiveDate)>,
+}
+
+impl SyncReport {
+    /// Whether every published archive is stored locally.
+    pub fn is_complete(&self) -> bool {
+        self.monthly.failed.is_empty() && self.daily.failed.is_empty()
+    }
+}
+
+/// Server errors, timeouts and checksum mismatches (e.g. caused by a truncated
+/// transfer) are likely to resolve themselves, and are therefore worth retrying.
+fn is_transient(error: &Error) -> bool {
+    match error {
+        Error::Http(e) => {
+            e.is_timeout() || e.status().is_some_and(|status| status.is_server_error())
+        }
+        Error::Checksum(_) => true,
+        _ => false,
+    }
+}
+
+fn record_download(
+    (date, result): (NaiveDate, Result<usize, Error>),
+    report: &mut DownloadReport,
+    frequency: &ArchiveFrequency,
     symbol: &str,
-    interval: &str,
-) -> Result<(), Error>
+    dataset: &Dataset,
+    options: &DownloadOptions,
+) {
+    let file = get_local_file_name(frequency, symbol, dataset, &date);
+    match result {
+        Ok(bytes) => {
+            options.report(DownloadProgress::Completed { file, bytes });
+            report.downloaded.push(date);
+        }
+        Err(Error::ArchiveNotAvailable(_)) => {
+            options.report(DownloadProgress::NotAvailable(file));
+            report.missing.push(date);
+        }
+        Err(e) => {
+            options.report(DownloadProgress::Failed {
+                file,
+                reason: e.to_string(),
+            });
+            report.failed.push((date, e));
+        }
+    }
+}
+
+/// Order in which loaded trades are returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeOrder {
+    Id,
+    Time,
+}
+
+/// Outcome of verifying locally stored archives against their checksums.
+#[derive(Debug, Default)]
+pub struct VerificationReport {
+    /// Archives whose digest matches the stored checksum.
+    pub verified: Vec<PathBuf>,
+    /// Archives whose digest does not match the stored checksum.
+    pub corrupt: Vec<PathBuf>,
+    /// Archives without a stored (or with an unreadable) checksum.
+    pub missing_checksum: Vec<PathBuf>,
+    /// Locations corrupt archives were moved to, if quarantined.
+    pub quarantined: Vec<PathBuf>,
+}
+
+impl VerificationReport {
+    pub fn is_valid(&self) -> bool {
+        self.corrupt.is_empty()
+    }
+}
+
+/// Re-hashes every archive stored under `root` (e.g. `data/`) and compares it
+/// against the checksum stored next to it. When `quarantine` is specified,
+/// corrupt archives (and their checksums) are moved into it, retaining their
+/// path relative to `root`, so that they are downloaded again on the next run.
+pub fn verify_local_archives<P, Q>(
+    root: P,
+    quarantine: Option<Q>,
+) -> Result<VerificationReport, Error>
 where
-    I: IntoIterator<Item = NaiveDate>,
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
 {
-    let client = reqwest::Client::new();
-    for date in dates {
-        // Data is only downloaded if not yet available.
-        if !is_saved(frequency, symbol, interval, &date) {
-            let local_path = get_local_file_path(frequency, symbol, interval);
-            let local_file = get_local_file_name(symbol, interval, &date);
-            let path = Path::new(&local_path).join(&local_file);
-            let file =
-                retrieve_historical_data(&client, frequency, symbol, interval, &date).await?;
-            async_write_safely(path, &file).await?;
-            println!("{}", &local_file);
-        }
-    }
-    Ok(())
+    let root = root.as_ref();
+    let mut report = VerificationReport::default();
+
+    let archives = identify_files_recursively(root)?
+        .into_iter()
+        .filter(|path| path.extension().is_some_and(|extension| extension == "zip"));
+
+    for archive in archives {
+        let checksum_path = PathBuf::from(get_checksum_file_name(&archive.to_string_lossy()));
+        let expected = std::fs::read_to_string(&checksum_path)
+            .ok()
+            .and_then(|content| parse_checksum(&content));
+
+        let Some(expected) = expected else {
+            report.missing_checksum.push(archive);
+            continue;
+        };
+
+        if sha256_file_digest(&archive)? == expected {
+            report.verified.push(archive);
+            continue;
+        }
+
+        if let Some(quarantine) = &quarantine {
+            let relative = archive.strip_prefix(root).unwrap_or(&archive);
+            let destination = quarantine.as_ref().join(relative);
+            if let Some(dir) = destination.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::rename(&archive, &destination)?;
+            std::fs::rename(
+                &checksum_path,
+                get_checksum_file_name(&destination.to_string_lossy()),
+            )?;
+            report.quarantined.push(destination);
+        }
+        report.corrupt.push(archive);
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    /// Responds to a single request with the specified raw response, or
+    /// never responds if `None`.
+    fn serve_once(response: Option<&'static str>) -> String {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = socket.read(&mut request);
+            match response {
+                Some(response) => socket.write_all(response.as_bytes()).unwrap(),
+                None => std::thread::sleep(Duration::from_secs(5)),
+            }
+        });
+        format!("http://{address}")
+    }
+
+    async fn http_error(response: Option<&'static str>) -> Error {
+        let url = serve_once(response);
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        let result = async { client.get(url).send().await?.error_for_status() }.await;
+        Error::Http(result.unwrap_err())
+    }
+
+    #[tokio::test]
+    async fn transient_errors() {
+        let timeout = http_error(None).await;
+        assert!(is_transient(&timeout));
+        let server_error = http_error(Some(
+            "HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\n\r\n",
+        ))
+        .await;
+        assert!(is_transient(&server_error));
+        assert!(is_transient(&Error::Checksum("mismatch".to_string())));
+
+        let not_found =
+            http_error(Some("HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n")).await;
+        assert!(!is_transient(&not_found));
+        assert!(!is_transient(&Error::ArchiveNotAvailable(
+            "archive".to_string()
+        )));
+        assert!(!is_transient(&Error::Parse("row".to_string())));
+    }
+
+    #[test]
+    fn backoff_is_bounded() {
+        let options = DownloadOptions::default();
+        assert_eq!(options.backoff(1), Duration::from_millis(500));
+        assert_eq!(options.backoff(3), Duration::from_secs(2));
+        assert_eq!(options.backoff(64), options.max_backoff);
+        assert_eq!(options.backoff(u32::MAX), options.max_backoff);
+    }
+
+    #[test]
+    fn plan_archives_across_month_boundary() {
+        let plan = plan_archives(date(2025, 5, 20), date(2025, 6, 30), date(2025, 6, 4));
+        assert_eq!(
+            plan,
+            vec![
+                (ArchiveFrequency::Monthly, date(2025, 5, 1)),
+                (ArchiveFrequency::Daily, date(2025, 6, 1)),
+                (ArchiveFrequency::Daily, date(2025, 6, 2)),
+                (ArchiveFrequency::Daily, date(2025, 6, 3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn plan_archives_on_first_of_month() {
+        // Neither the current month nor any of its days have been published
+        // yet, and May is only covered by daily archives until its monthly
+        // archive is published.
+        let plan = plan_archives(date(2025, 4, 1), date(2025, 6, 30), date(2025, 6, 1));
+        assert_eq!(
+            last_completed_month(date(2025, 6, 1)),
+            Some(date(2025, 5, 1))
+        );
+        let plan =
+            fall_back_to_daily_archives(plan, date(2025, 4, 1), date(2025, 6, 30), |month| {
+                *month == date(2025, 5, 1)
+            });
+
+        let mut expected = vec![(ArchiveFrequency::Monthly, date(2025, 4, 1))];
+        expected.extend(
+            generate_daily_date_range(date(2025, 5, 1), date(2025, 5, 31))
+                .map(|day| (ArchiveFrequency::Daily, day)),
+        );
+        assert_eq!(plan, expected);
+    }
+
+    #[test]
+    fn fall_back_to_daily_archives_within_range() {
+        let plan = plan_archives(date(2025, 5, 20), date(2025, 6, 3), date(2025, 6, 5));
+        let plan = fall_back_to_daily_archives(plan, date(2025, 5, 20), date(2025, 6, 3), |_| true);
+        let expected: Vec<(ArchiveFrequency, NaiveDate)> =
+            generate_daily_date_range(date(2025, 5, 20), date(2025, 6, 3))
+                .map(|day| (ArchiveFrequency::Daily, day))
+                .collect();
+        assert_eq!(plan, expected);
+
+        // Published monthly archives are kept.
+        let plan = plan_archives(date(2025, 4, 1), date(2025, 5, 31), date(2025, 6, 5));
+        let kept =
+            fall_back_to_daily_archives(plan.clone(), date(2025, 4, 1), date(2025, 5, 31), |_| {
+                false
+            });
+        assert_eq!(kept, plan);
+    }
+
+    #[test]
+    fn plan_archives_for_completed_range() {
+        let plan = plan_archives(date(2024, 11, 15), date(2025, 1, 10), date(2025, 6, 4));
+        assert_eq!(
+            plan,
+            vec![
+                (ArchiveFrequency::Monthly, date(2024, 11, 1)),
+                (ArchiveFrequency::Monthly, date(2024, 12, 1)),
+                (ArchiveFrequency::Monthly, date(2025, 1, 1)),
+            ]
+        );
+    }
+
+    /// File served by `serve_files`, i.e. its name, status and body.
+    pub(crate) type StubFile = (String, &'static str, Vec<u8>);
+
+    /// Responds to every request with the file of the requested name
+    /// (ignoring the query), or `404 Not Found` if none is specified.
+    pub(crate) fn serve_files(files: Vec<StubFile>) -> String {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for socket in listener.incoming() {
+                let mut socket = socket.unwrap();
+                let mut request = [0; 4096];
+                let length = socket.read(&mut request).unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..length]);
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                let path = path.split('?').next().unwrap_or_default();
+                let file = path.rsplit('/').next().unwrap_or_default();
+                let (status, body) = files
+                    .iter()
+                    .find(|(name, _, _)| name == file)
+                    .map(|(_, status, body)| (*status, body.as_slice()))
+                    .unwrap_or(("404 Not Found", b"<Error>NoSuchKey</Error>".as_slice()));
+                let header = format!(
+                    "HTTP/1.1 {status}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = socket.write_all(header.as_bytes());
+                let _ = socket.write_all(body);
+            }
+        });
+        format!("http://{address}")
+    }
+
+    /// Archive and its checksum, as published on data.binance.vision.
+    pub(crate) fn published(name: &str, archive: &[u8]) -> Vec<StubFile> {
+        vec![
+            (
+                get_checksum_file_name(name),
+                "200 OK",
+                checksum_of(name, archive).into_bytes(),
+            ),
+            (name.to_string(), "200 OK", archive.to_vec()),
+        ]
+    }
+
+    fn checksum_of(name: &str, content: &[u8]) -> String {
+        format!("{}  {name}", crate::fs::checksum::sha256_digest(&content))
+    }
+
+    /// Zip archive containing a single file.
+    pub(crate) fn zip_archive(name: &str, content: &str) -> Vec<u8> {
+        use std::io::Write;
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        writer
+            .start_file(name, zip::write::SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(content.as_bytes()).unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    pub(crate) fn daily_archive_name(symbol: &str, dataset: &Dataset, date: NaiveDate) -> String {
+        get_remove_file_name(&ArchiveFrequency::Daily, symbol, dataset, &date)
+    }
+
+    /// Empty directory in the temporary directory, unique to this process.
+    pub(crate) fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("binny-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    pub(crate) fn stub_client(base_url: String, local_root: &Path) -> HistoricalClient {
+        HistoricalClient::new(HistoricalConfig {
+            base_url,
+            local_root: local_root.to_path_buf(),
+            timeout: Some(Duration::from_secs(5)),
+            cache: false,
+            listing_dates: Vec::new(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    const ARCHIVE: &[u8] = b"PK archive content";
+
+    fn klines() -> Dataset {
+        Dataset::Klines("1h".to_string())
+    }
+
+    fn archive_name() -> String {
+        daily_archive_name("BTCUSDT", &klines(), date(2025, 5, 1))
+    }
+
+    async fn download(client: &HistoricalClient, max_retries: u32) -> DownloadReport {
+        let options = DownloadOptions {
+            max_retries,
+            initial_backoff: Duration::from_millis(1),
+            ..Default::default()
+        };
+        client
+            .retrieve_and_save_dataset_range(
+                [date(2025, 5, 1)],
+                &ArchiveFrequency::Daily,
+                &Market::Spot,
+                "BTCUSDT",
+                &klines(),
+                &options,
+            )
+            .await
+            .unwrap()
+    }
+
+    fn saved_archives(root: &Path) -> Vec<PathBuf> {
+        if !root.exists() {
+            return Vec::new();
+        }
+        identify_files_recursively(root)
+            .unwrap()
+            .into_iter()
+            .filter(|path| path.extension().is_some_and(|extension| extension == "zip"))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn unpublished_archives_are_missing() {
+        let root = temp_dir("missing");
+        let client = stub_client(serve_files(Vec::new()), &root);
+
+        let report = download(&client, 0).await;
+        assert_eq!(report.missing, vec![date(2025, 5, 1)]);
+        assert!(report.downloaded.is_empty());
+        assert!(report.failed.is_empty());
+        assert!(saved_archives(&root).is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn error_bodies_are_never_saved() {
+        let root = temp_dir("errors");
+        let archive = archive_name();
+
+        // Server error while retrieving the archive.
+        let mut files = published(&archive, ARCHIVE);
+        files[1] = (
+            archive.clone(),
+            "500 Internal Server Error",
+            b"<Error>InternalError</Error>".to_vec(),
+        );
+        let client = stub_client(serve_files(files), &root);
+        let report = download(&client, 1).await;
+        assert_eq!(report.failed.len(), 1);
+        assert!(matches!(report.failed[0].1, Error::Http(_)));
+
+        // Error page served instead of the checksum.
+        let mut files = published(&archive, ARCHIVE);
+        files[0].2 = b"<html>Service Unavailable</html>".to_vec();
+        let client = stub_client(serve_files(files), &root);
+        let report = download(&client, 0).await;
+        assert_eq!(report.failed.len(), 1);
+        assert!(matches!(report.failed[0].1, Error::Checksum(_)));
+        assert!(saved_archives(&root).is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn checksum_mismatch_is_rejected() {
+        let root = temp_dir("mismatch");
+        let archive = archive_name();
+        let mut files = published(&archive, ARCHIVE);
+        files[0].2 = checksum_of(&archive, b"other content").into_bytes();
+        let client = stub_client(serve_files(files), &root);
+
+        let report = download(&client, 2).await;
+        assert!(report.downloaded.is_empty());
+        assert_eq!(report.failed.len(), 1);
+        assert!(matches!(report.failed[0].1, Error::Checksum(_)));
+        assert!(saved_archives(&root).is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn corrupt_archives_are_quarantined() {
+        let root = temp_dir("verify");
+        let quarantine = temp_dir("quarantine");
+        let archive = archive_name();
+        let client = stub_client(serve_files(published(&archive, ARCHIVE)), &root);
+
+        let report = download(&client, 0).await;
+        assert_eq!(report.downloaded, vec![date(2025, 5, 1)]);
+        let saved = saved_archives(&root);
+        assert_eq!(saved.len(), 1);
+        assert!(saved[0].ends_with(&archive));
+
+        let report = client.verify_local_archives(Some(&quarantine)).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.verified, saved);
+
+        std::fs::write(&saved[0], "truncated").unwrap();
+        let report = client.verify_local_archives(Some(&quarantine)).unwrap();
+        assert!(!report.is_valid());
+        assert_eq!(report.corrupt, saved);
+        assert_eq!(report.quarantined.len(), 1);
+        assert!(report.quarantined[0].is_file());
+        assert!(
+            PathBuf::from(get_checksum_file_name(
+                &report.quarantined[0].to_string_lossy()
+            ))
+            .is_file()
+        );
+        assert!(saved_archives(&root).is_empty());
+
+        std::fs::remove_dir_all(&root).unwrap();
+        std::fs::remove_dir_all(&quarantine).unwrap();
+    }
 }
@@ -1,5 +1,11 @@
 pub mod account;
+pub mod catalog;
+pub mod combined;
+pub mod connection;
+pub mod depth;
+pub mod exchange_info;
 pub mod historical;
+pub mod rest;
 pub mod stream;
 pub mod trade_book;
 pub mod trading;
@@ -0,0 +1,398 @@
+use chrono::{NaiveDate, Utc};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::binance::exchange_info::ExchangeInfo;
+use crate::binance::historical::Market;
+use crate::binance::stream::KlineInterval;
+use crate::binance::trade_book::PartialDepthStream;
+use crate::errors::Error;
+use crate::fs::write::async_write_safely;
+use crate::models::HistoricalKlineEvent;
+
+// Maximum number of klines returned by a single request.
+const KLINES_LIMIT: usize = 1000;
+
+// Response header containing the request weight used, by the requesting
+// IP address, during the current minute.
+const USED_WEIGHT_HEADER: &str = "x-mbx-used-weight-1m";
+
+const MINUTE_MS: i64 = 60_000;
+
+/// Configuration of a `RestClient`.
+#[derive(Debug, Clone)]
+pub struct RestConfig {
+    pub market: Market,
+    /// Overrides the REST API of the market, e.g. to use the testnet.
+    pub base_url: Option<String>,
+    pub timeout: Option<Duration>,
+    pub proxy: Option<String>,
+    pub user_agent: Option<String>,
+    /// Request weight allowed per minute, defaults to the limit of the market.
+    pub weight_limit: Option<u32>,
+    /// Number of times a rate limited (HTTP 429) request is retried.
+    pub max_retries: u32,
+}
+
+impl Default for RestConfig {
+    fn default() -> Self {
+        Self {
+            market: Market::Spot,
+            base_url: None,
+            timeout: Some(Duration::from_secs(30)),
+            proxy: None,
+            user_agent: None,
+            weight_limit: None,
+            max_retries: 3,
+        }
+    }
+}
+
+/// Request weight used during a minute. Requests which would exceed the
+/// limit are delayed until the next minute.
+#[derive(Debug)]
+struct RequestWeight {
+    limit: u32,
+    used: u32,
+    /// Minute (since the epoch) `used` applies to.
+    minute: i64,
+}
+
+impl RequestWeight {
+    /// Reserves `weight`, returning how long to wait before sending the request.
+    fn reserve(&mut self, weight: u32, now: i64) -> Duration {
+        let minute = now.div_euclid(MINUTE_MS);
+        if minute > self.minute {
+            self.minute = minute;
+            self.used = 0;
+        }
+        if self.used > 0 && self.used + weight > self.limit {
+            self.minute += 1;
+            self.used = 0;
+        }
+        self.used += weight;
+        Duration::from_millis((self.minute * MINUTE_MS - now).max(0) as u64)
+    }
+
+    /// Updates the used weight with the weight reported by the server,
+    /// which includes requests made by other clients using the same IP.
+    fn update(&mut self, used: u32, now: i64) {
+        if now.div_euclid(MINUTE_MS) == self.minute {
+            self.used = self.used.max(used);
+        }
+    }
+}
+
+/// Unauthenticated client of the Binance REST API, which keeps track
+/// of the request weight used so the rate limit is not exceeded.
+#[derive(Debug, Clone)]
+pub struct RestClient {
+    market: Market,
+    base_url: String,
+    client: reqwest::Client,
+    weight: Arc<Mutex<RequestWeight>>,
+    max_retries: u32,
+}
+
+impl RestClient {
+    pub fn new(config: RestConfig) -> Result<Self, Error> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = config.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(proxy) = &config.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+        if let Some(user_agent) = &config.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+
+        let base_url = config
+            .base_url
+            .as_deref()
+            .unwrap_or(config.market.rest_end_point());
+        let limit = config.weight_limit.unwrap_or(match config.market {
+            Market::Spot => 6000,
+            Market::UsdMFutures | Market::CoinMFutures => 2400,
+        });
+
+        Ok(Self {
+            market: config.market,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client: builder.build()?,
+            weight: Arc::new(Mutex::new(RequestWeight {
+                limit,
+                used: 0,
+                minute: 0,
+            })),
+            max_retries: config.max_retries,
+        })
+    }
+
+    pub fn market(&self) -> Market {
+        self.market
+    }
+
+    /// Sends a GET request to `path`, relative to the REST API of the market,
+    /// e.g. `klines`, once `weight` is available within the rate limit.
+    async fn send(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+        weight: u32,
+    ) -> Result<reqwest::Response, Error> {
+        let url = format!("{}/{}", self.base_url, path.trim_start_matches('/'));
+        let mut attempt = 0;
+        loop {
+            let wait = self
+                .weight
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .reserve(weight, Utc::now().timestamp_millis());
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+
+            let response = self.client.get(&url).query(query).send().await?;
+            if let Some(used) = response
+                .headers()
+                .get(USED_WEIGHT_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u32>().ok())
+            {
+                self.weight
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .update(used, Utc::now().timestamp_millis());
+            }
+
+            // Requests exceeding the rate limit are to be retried after the
+            // specified number of seconds. Repeatedly doing so results in an
+            // IP ban (HTTP 418), hence the number of retries is limited.
+            if response.status() == StatusCode::TOO_MANY_REQUESTS && attempt < self.max_retries {
+                attempt += 1;
+                let delay = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .map_or(Duration::from_millis(MINUTE_MS as u64), Duration::from_secs);
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+
+            return Ok(response.error_for_status()?);
+        }
+    }
+
+    /// Sends a GET request, see `send`, deserializing the JSON response.
+    pub async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+        weight: u32,
+    ) -> Result<T, Error> {
+        Ok(self.send(path, query, weight).await?.json().await?)
+    }
+
+    fn klines_weight(&self) -> u32 {
+        match self.market {
+            Market::Spot => 2,
+            // Futures weights depend on the limit, which is 1000.
+            Market::UsdMFutures | Market::CoinMFutures => 5,
+        }
+    }
+
+    /// Retrieves the klines opened at or after `start` and before `end`
+    /// (milliseconds), paginating as required. The most recent kline may
+    /// not have closed yet, i.e. its close time `T` is in the future.
+    pub async fn klines(
+        &self,
+        symbol: &str,
+        interval: &KlineInterval,
+        start: i64,
+        end: Option<i64>,
+    ) -> Result<Vec<HistoricalKlineEvent>, Error> {
+        let mut klines: Vec<HistoricalKlineEvent> = Vec::new();
+        let mut cursor = start;
+        while end.is_none_or(|end| cursor < end) {
+            let mut query = vec![
+                ("symbol", symbol.to_uppercase()),
+                ("interval", interval.to_string()),
+                ("startTime", cursor.to_string()),
+                ("limit", KLINES_LIMIT.to_string()),
+            ];
+            // The end time is inclusive.
+            if let Some(end) = end {
+                query.push(("endTime", (end - 1).to_string()));
+            }
+
+            let rows: Vec<Vec<serde_json::Value>> =
+                self.get("klines", &query, self.klines_weight()).await?;
+            let received = rows.len();
+            for row in rows {
+                klines.push(HistoricalKlineEvent::try_from(row)?);
+            }
+
+            match klines.last() {
+                Some(last) if received == KLINES_LIMIT => cursor = last.t + 1,
+                _ => break,
+            }
+        }
+        Ok(klines)
+    }
+
+    /// Retrieves the date of the first kline of `symbol`, i.e. the date the
+    /// symbol was listed, or `None` if no klines exist.
+    pub async fn listing_date(&self, symbol: &str) -> Result<Option<NaiveDate>, Error> {
+        let weight = match self.market {
+            Market::Spot => 2,
+            Market::UsdMFutures | Market::CoinMFutures => 1,
+        };
+        let query = [
+            ("symbol", symbol.to_uppercase()),
+            ("interval", KlineInterval::OneDay.to_string()),
+            ("startTime", "0".to_string()),
+            ("limit", "1".to_string()),
+        ];
+        let rows: Vec<Vec<serde_json::Value>> = self.get("klines", &query, weight).await?;
+        let listing_date = rows
+            .first()
+            .and_then(|row| row.first())
+            .and_then(|open_time| open_time.as_i64())
+            .and_then(chrono::DateTime::from_timestamp_millis)
+            .map(|datetime| datetime.date_naive());
+        Ok(listing_date)
+    }
+
+    /// Retrieves a snapshot of the order book of `symbol`, up to `limit`
+    /// levels per side (at most 5000 for spot, 1000 for futures).
+    pub async fn depth(&self, symbol: &str, limit: u16) -> Result<PartialDepthStream, Error> {
+        let weight = match (self.market, limit) {
+            (Market::Spot, 0..=100) => 5,
+            (Market::Spot, 101..=500) => 25,
+            (Market::Spot, 501..=1000) => 50,
+            (Market::Spot, _) => 250,
+            (_, 0..=50) => 2,
+            (_, 51..=100) => 5,
+            (_, 101..=500) => 10,
+            (_, _) => 20,
+        };
+        let query = [
+            ("symbol", symbol.to_uppercase()),
+            ("limit", limit.to_string()),
+        ];
+        self.get("depth", &query, weight).await
+    }
+
+    /// Retrieves the exchange info of every symbol, as received.
+    pub async fn exchange_info_json(&self) -> Result<String, Error> {
+        let weight = match self.market {
+            Market::Spot => 20,
+            Market::UsdMFutures | Market::CoinMFutures => 1,
+        };
+        Ok(self.send("exchangeInfo", &[], weight).await?.text().await?)
+    }
+
+    pub async fn exchange_info(&self) -> Result<ExchangeInfo, Error> {
+        ExchangeInfo::from_json(&self.exchange_info_json().await?)
+    }
+
+    /// Retrieves the exchange info, saving it to `path` (see
+    /// `get_exchange_info_cache_path`). Exchange info saved less than
+    /// `max_age` ago is used instead, regardless of its age if `max_age` is
+    /// `None`. When the retrieval fails, exchange info saved previously is
+    /// used regardless of its age, so backtests are able to run offline.
+    pub async fn cached_exchange_info<P: AsRef<Path>>(
+        &self,
+        path: P,
+        max_age: Option<Duration>,
+    ) -> Result<ExchangeInfo, Error> {
+        let path = path.as_ref();
+        let age = std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .map(|modified| modified.elapsed().unwrap_or_default());
+        if let Some(age) = age
+            && max_age.is_none_or(|max_age| age < max_age)
+        {
+            return ExchangeInfo::load(path);
+        }
+
+        match self.exchange_info_json().await {
+            Ok(json) => {
+                let info = ExchangeInfo::from_json(&json)?;
+                async_write_safely(path, &json).await?;
+                Ok(info)
+            }
+            Err(_) if age.is_some() => ExchangeInfo::load(path),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weight(limit: u32) -> RequestWeight {
+        RequestWeight {
+            limit,
+            used: 0,
+            minute: 0,
+        }
+    }
+
+    #[test]
+    fn reserve_within_limit() {
+        let mut weight = weight(10);
+        let now = 5 * MINUTE_MS + 1_000;
+        assert_eq!(weight.reserve(4, now), Duration::ZERO);
+        assert_eq!(weight.reserve(6, now), Duration::ZERO);
+        assert_eq!(weight.used, 10);
+    }
+
+    #[test]
+    fn reserve_beyond_limit_waits_for_next_minute() {
+        let mut weight = weight(10);
+        let now = 5 * MINUTE_MS + 45_000;
+        weight.reserve(8, now);
+        assert_eq!(weight.reserve(4, now), Duration::from_secs(15));
+        assert_eq!((weight.minute, weight.used), (6, 4));
+
+        // The weight used resets once the minute has passed.
+        assert_eq!(weight.reserve(6, 6 * MINUTE_MS + 1), Duration::ZERO);
+        assert_eq!(weight.reserve(1, 7 * MINUTE_MS), Duration::ZERO);
+        assert_eq!((weight.minute, weight.used), (7, 1));
+    }
+
+    #[test]
+    fn reserve_heavier_than_limit() {
+        // Requests heavier than the limit are still sent, once nothing
+        // else has been used during the minute.
+        let mut weight = weight(10);
+        assert_eq!(weight.reserve(25, MINUTE_MS), Duration::ZERO);
+        assert_eq!(weight.reserve(1, MINUTE_MS), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn update_with_weight_used_by_server() {
+        let mut weight = weight(10);
+        let now = 5 * MINUTE_MS;
+        weight.reserve(2, now);
+        // Includes weight used by other clients.
+        weight.update(9, now + 1_000);
+        assert_eq!(weight.used, 9);
+        assert_eq!(weight.reserve(2, now + 2_000), Duration::from_secs(58));
+
+        // The server reports less than reserved by requests in flight.
+        weight.update(1, 6 * MINUTE_MS);
+        assert_eq!(weight.used, 2);
+        // Reports of a previous minute are ignored.
+        weight.update(10, now + 3_000);
+        assert_eq!(weight.used, 2);
+    }
+}
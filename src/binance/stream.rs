@@ -1,15 +1,27 @@
+use std::marker::PhantomData;
+use std::pin::Pin;
 use std::sync::mpsc::Sender;
+use std::task::{Context, Poll};
 use std::thread;
 
+use futures_core::Stream;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use tungstenite::{Message, connect};
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+use tokio_util::sync::{CancellationToken, DropGuard};
 use url::Url;
 
-use crate::{errors, models};
+use crate::binance::connection::{ReconnectOptions, StreamItem, run_connection, spawn_connection};
+use crate::binance::trade_book::{DiffDepthStream, PartialDepthStream};
+use crate::errors;
+use crate::models::{
+    AggTradeEvent, AvgPriceEvent, BookTickerEvent, KlineEvent, MiniTickerEvent,
+    RollingWindowTickerEvent, TickerEvent, TradeEvent,
+};
 
 // TODO: There is a "Average Price" websocket, use this for the MACD?
 
-static BASE_END_POINT: &str = "wss://stream.binance.com:9443";
+pub(crate) static BASE_END_POINT: &str = "wss://stream.binance.com:9443";
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
@@ -72,35 +84,339 @@ impl std::fmt::Display for KlineInterval {
     }
 }
 
-// TODO: make this more abstract in order to allow streams
-// other than Klines to be received.
+impl KlineInterval {
+    /// Length of the interval in milliseconds, `None` for monthly
+    /// klines since the length of a month varies.
+    pub fn duration_ms(&self) -> Option<i64> {
+        const SECOND: i64 = 1000;
+        const MINUTE: i64 = 60 * SECOND;
+        const HOUR: i64 = 60 * MINUTE;
+        const DAY: i64 = 24 * HOUR;
+        let duration = match self {
+            Self::OneSecond => SECOND,
+            Self::OneMinute => MINUTE,
+            Self::ThreeMinutes => 3 * MINUTE,
+            Self::FiveMinutes => 5 * MINUTE,
+            Self::FifteenMinutes => 15 * MINUTE,
+            Self::ThirtyMinutes => 30 * MINUTE,
+            Self::OneHour => HOUR,
+            Self::TwoHours => 2 * HOUR,
+            Self::FourHours => 4 * HOUR,
+            Self::SixHours => 6 * HOUR,
+            Self::EightHours => 8 * HOUR,
+            Self::TwelveHours => 12 * HOUR,
+            Self::OneDay => DAY,
+            Self::ThreeDays => 3 * DAY,
+            Self::OneWeek => 7 * DAY,
+            Self::OneMonth => return None,
+        };
+        Some(duration)
+    }
 
-// TODO: make the below fn more modular because
-// the trim_matches() should not be in the below.
+    /// Open time of the kline containing `t`, both in milliseconds. Klines
+    /// are aligned to UTC boundaries, with weekly klines opening on Monday
+    /// and monthly klines on the first day of the month.
+    pub fn open_time_containing(&self, t: i64) -> Option<i64> {
+        // The epoch was a Thursday, while weekly klines open on Mondays.
+        const WEEK_OFFSET: i64 = 4 * 24 * 60 * 60 * 1000;
+        match (self, self.duration_ms()) {
+            (Self::OneWeek, Some(duration)) => {
+                Some((t - WEEK_OFFSET).div_euclid(duration) * duration + WEEK_OFFSET)
+            }
+            (_, Some(duration)) => Some(t.div_euclid(duration) * duration),
+            (_, None) => {
+                let date = chrono::DateTime::from_timestamp_millis(t)?.date_naive();
+                let month = chrono::Datelike::with_day(&date, 1)?;
+                Some(
+                    month
+                        .and_time(chrono::NaiveTime::MIN)
+                        .and_utc()
+                        .timestamp_millis(),
+                )
+            }
+        }
+    }
 
-pub fn stream_to_channel(
-    symbol: &str,
-    interval: &KlineInterval,
-    sender: Sender<models::KlineEvent>,
-) -> thread::JoinHandle<Result<(), errors::Error>> {
-    //let stream = format!("{symbol}@trade");
-    let stream = format!("{symbol}@kline_{interval}");
-    let end_point = format!("{BASE_END_POINT}/ws/{stream}");
-    let url = Url::parse(&end_point).expect("Invalid URL");
+    /// Open time of the kline following the kline opened at `t`,
+    /// both in milliseconds.
+    pub fn next_open_time(&self, t: i64) -> Option<i64> {
+        match self.duration_ms() {
+            Some(duration) => t.checked_add(duration),
+            None => chrono::DateTime::from_timestamp_millis(t)?
+                .checked_add_months(chrono::Months::new(1))
+                .map(|datetime| datetime.timestamp_millis()),
+        }
+    }
+}
 
-    thread::spawn(move || {
-        let (mut socket, _) = connect(url)?;
+/// Rolling windows of `<symbol>@ticker_<window_size>` streams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TickerWindow {
+    OneHour,
+    FourHours,
+    OneDay,
+}
 
-        while let Ok(msg) = socket.read_message() {
-            if let Message::Text(text) = msg {
-                let text = text.trim_matches('"');
-                let parsed: models::KlineEvent = serde_json::from_str(&text)?;
-                if sender.send(parsed).is_err() {
-                    break;
-                }
-            }
+impl std::fmt::Display for TickerWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::OneHour => "1h",
+            Self::FourHours => "4h",
+            Self::OneDay => "1d",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// Number of levels of `<symbol>@depth<levels>` streams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DepthLevels {
+    Five,
+    Ten,
+    Twenty,
+}
+
+impl std::fmt::Display for DepthLevels {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Five => "5",
+            Self::Ten => "10",
+            Self::Twenty => "20",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// Frequency at which depth streams push updates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum UpdateSpeed {
+    /// Every 1000ms.
+    #[default]
+    Standard,
+    /// Every 100ms.
+    Fast,
+}
+
+impl UpdateSpeed {
+    fn suffix(&self) -> &'static str {
+        match self {
+            Self::Standard => "",
+            Self::Fast => "@100ms",
         }
+    }
+}
+
+/// Describes a market stream, e.g. `btcusdt@trade`, along with the
+/// type of the events it pushes.
+///
+/// ```ignore
+/// let (tx, rx) = std::sync::mpsc::channel();
+/// let handle = stream_to_channel(MarketStream::trade("btcusdt"), tx);
+/// ```
+#[derive(Debug)]
+pub struct MarketStream<E> {
+    name: String,
+    event: PhantomData<fn() -> E>,
+}
+
+// Not derived, since that would require `E: Clone`.
+impl<E> Clone for MarketStream<E> {
+    fn clone(&self) -> Self {
+        Self::new(self.name.clone())
+    }
+}
+
+impl<E> MarketStream<E> {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            event: PhantomData,
+        }
+    }
+
+    /// Stream name, e.g. `btcusdt@kline_1m`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn end_point(&self) -> Url {
+        let end_point = format!("{BASE_END_POINT}/ws/{}", self.name);
+        Url::parse(&end_point).expect("Invalid URL")
+    }
+}
 
-        Ok(())
+impl MarketStream<TradeEvent> {
+    pub fn trade(symbol: &str) -> Self {
+        Self::new(format!("{}@trade", symbol.to_lowercase()))
+    }
+}
+
+impl MarketStream<AggTradeEvent> {
+    pub fn agg_trade(symbol: &str) -> Self {
+        Self::new(format!("{}@aggTrade", symbol.to_lowercase()))
+    }
+}
+
+impl MarketStream<KlineEvent> {
+    pub fn kline(symbol: &str, interval: &KlineInterval) -> Self {
+        Self::new(format!("{}@kline_{interval}", symbol.to_lowercase()))
+    }
+}
+
+impl MarketStream<MiniTickerEvent> {
+    pub fn mini_ticker(symbol: &str) -> Self {
+        Self::new(format!("{}@miniTicker", symbol.to_lowercase()))
+    }
+}
+
+impl MarketStream<TickerEvent> {
+    pub fn ticker(symbol: &str) -> Self {
+        Self::new(format!("{}@ticker", symbol.to_lowercase()))
+    }
+}
+
+impl MarketStream<RollingWindowTickerEvent> {
+    pub fn rolling_window_ticker(symbol: &str, window: &TickerWindow) -> Self {
+        Self::new(format!("{}@ticker_{window}", symbol.to_lowercase()))
+    }
+}
+
+impl MarketStream<AvgPriceEvent> {
+    pub fn avg_price(symbol: &str) -> Self {
+        Self::new(format!("{}@avgPrice", symbol.to_lowercase()))
+    }
+}
+
+impl MarketStream<BookTickerEvent> {
+    pub fn book_ticker(symbol: &str) -> Self {
+        Self::new(format!("{}@bookTicker", symbol.to_lowercase()))
+    }
+}
+
+impl MarketStream<PartialDepthStream> {
+    pub fn partial_depth(symbol: &str, levels: &DepthLevels, speed: &UpdateSpeed) -> Self {
+        Self::new(format!(
+            "{}@depth{levels}{}",
+            symbol.to_lowercase(),
+            speed.suffix()
+        ))
+    }
+}
+
+impl MarketStream<DiffDepthStream> {
+    pub fn diff_depth(symbol: &str, speed: &UpdateSpeed) -> Self {
+        Self::new(format!("{}@depth{}", symbol.to_lowercase(), speed.suffix()))
+    }
+}
+
+/// Connects to `stream` on a separate thread, sending every event received
+/// to `sender` until the receiver is closed. Lost connections are
+/// re-established using the default `ReconnectOptions`, without notice, see
+/// `stream_to_channel_with_options` to be notified.
+pub fn stream_to_channel<E>(
+    stream: MarketStream<E>,
+    sender: Sender<E>,
+) -> thread::JoinHandle<Result<(), errors::Error>>
+where
+    E: DeserializeOwned + Send + 'static,
+{
+    spawn_connection(
+        stream.end_point(),
+        ReconnectOptions::default(),
+        move |item| match item.parse()? {
+            StreamItem::Event(event) => Ok(sender.send(event).is_ok()),
+            StreamItem::Connection(_) => Ok(true),
+        },
+    )
+}
+
+/// Similar to `stream_to_channel`, however using the specified
+/// `ReconnectOptions`, and sending the changes in the state of the
+/// connection to `sender` as well, in between the events.
+pub fn stream_to_channel_with_options<E>(
+    stream: MarketStream<E>,
+    sender: Sender<StreamItem<E>>,
+    options: ReconnectOptions,
+) -> thread::JoinHandle<Result<(), errors::Error>>
+where
+    E: DeserializeOwned + Send + 'static,
+{
+    spawn_connection(stream.end_point(), options, move |item| {
+        Ok(sender.send(item.parse()?).is_ok())
     })
 }
+
+/// Events of a market stream, along with the changes in the state of its
+/// connection, see `subscribe`.
+#[derive(Debug)]
+pub struct EventStream<E> {
+    receiver: UnboundedReceiver<Result<StreamItem<E>, errors::Error>>,
+    // Closes the connection once the stream is dropped.
+    _cancel: DropGuard,
+}
+
+impl<E> EventStream<E> {
+    /// Receives the next item, `None` once the stream has ended.
+    pub async fn recv(&mut self) -> Option<Result<StreamItem<E>, errors::Error>> {
+        self.receiver.recv().await
+    }
+
+    /// Receives the next item if one is available, without waiting.
+    pub fn try_recv(&mut self) -> Option<Result<StreamItem<E>, errors::Error>> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl<E> Stream for EventStream<E> {
+    type Item = Result<StreamItem<E>, errors::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().receiver.poll_recv(cx)
+    }
+}
+
+/// Receives the events of `stream` on the current tokio runtime, rather than
+/// on a separate thread. The connection is closed once either `cancel` is
+/// cancelled or the returned stream is dropped. Lost connections are
+/// re-established according to `options`, which is reported in between the
+/// events. The stream ends with an error if this fails or if an event can't
+/// be deserialized.
+///
+/// ```ignore
+/// let cancel = CancellationToken::new();
+/// let mut trades = subscribe(MarketStream::trade("btcusdt"), ReconnectOptions::default(), cancel.clone());
+/// while let Some(item) = trades.recv().await {
+///     match item? {
+///         StreamItem::Event(trade) => println!("{trade:?}"),
+///         StreamItem::Connection(event) => println!("{event}"),
+///     }
+/// }
+/// ```
+pub fn subscribe<E>(
+    stream: MarketStream<E>,
+    options: ReconnectOptions,
+    cancel: CancellationToken,
+) -> EventStream<E>
+where
+    E: DeserializeOwned + Send + 'static,
+{
+    let url = stream.end_point();
+    let cancel = cancel.child_token();
+    let guard = cancel.clone().drop_guard();
+    let (sender, receiver) = unbounded_channel();
+
+    tokio::spawn(async move {
+        let result = run_connection(&url, &options, &cancel, |item| {
+            Ok(sender.send(Ok(item.parse()?)).is_ok())
+        })
+        .await;
+        if let Err(e) = result {
+            let _ = sender.send(Err(e));
+        }
+    });
+
+    EventStream {
+        receiver,
+        _cancel: guard,
+    }
+}
@@ -1,7 +1,22 @@
 use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::binance::connection::{ConnectionEvent, ReconnectOptions, StreamItem};
+use crate::binance::rest::RestClient;
+use crate::binance::stream::{MarketStream, UpdateSpeed, subscribe};
+use crate::decimal::Decimal;
+use crate::errors::Error;
 
 static BASE_END_POINT: &str = "wss://stream.binance.com:9443/ws";
 
+// Snapshots are heavy requests (up to 250 weight for spot), hence
+// retrieving them is backed off, and eventually given up, when the
+// book fails to synchronise.
+const SNAPSHOT_MAX_ATTEMPTS: u32 = 5;
+const SNAPSHOT_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
 // Order book price and quantity depth updates used to locally manage an order book.
 
 #[allow(non_snake_case)]
@@ -15,3 +30,453 @@ pub struct DiffDepthStream {
     pub b: Vec<[String; 2]>, // Bids: [price, quantity]
     pub a: Vec<[String; 2]>, // Asks: [price, quantity]
 }
+
+// Top bids and asks, i.e. a snapshot of the order book up to a number of levels.
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PartialDepthStream {
+    pub last_update_id: u64,    // Last update ID
+    pub bids: Vec<[String; 2]>, // Bids: [price, quantity]
+    pub asks: Vec<[String; 2]>, // Asks: [price, quantity]
+}
+
+/// Outcome of applying a diff depth event to an order book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthUpdate {
+    Applied,
+    /// The event only contains updates already included in the book.
+    Stale,
+    /// Updates between the book and the event are missing, hence the
+    /// book is no longer valid.
+    Gap,
+}
+
+fn parse_level(level: &[String; 2]) -> Result<(Decimal, Decimal), Error> {
+    Ok((level[0].parse()?, level[1].parse()?))
+}
+
+/// Sets the quantity of every level, removing levels without quantity.
+fn update_levels(
+    side: &mut BTreeMap<Decimal, Decimal>,
+    levels: &[[String; 2]],
+) -> Result<(), Error> {
+    for level in levels {
+        let (price, quantity) = parse_level(level)?;
+        if quantity.is_zero() {
+            side.remove(&price);
+        } else {
+            side.insert(price, quantity);
+        }
+    }
+    Ok(())
+}
+
+/// Local order book of a single symbol, using exact decimal prices and quantities.
+#[derive(Debug, Clone)]
+pub struct OrderBook {
+    pub symbol: String,
+    /// Update ID of the last update included in the book.
+    pub last_update_id: u64,
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+}
+
+impl OrderBook {
+    pub fn from_snapshot(symbol: &str, snapshot: &PartialDepthStream) -> Result<Self, Error> {
+        let mut book = Self {
+            symbol: symbol.to_uppercase(),
+            last_update_id: snapshot.last_update_id,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+        };
+        update_levels(&mut book.bids, &snapshot.bids)?;
+        update_levels(&mut book.asks, &snapshot.asks)?;
+        Ok(book)
+    }
+
+    /// Applies a diff depth event, unless it is stale or does not
+    /// directly follow the last update included in the book.
+    pub fn apply(&mut self, event: &DiffDepthStream) -> Result<DepthUpdate, Error> {
+        if event.u <= self.last_update_id {
+            return Ok(DepthUpdate::Stale);
+        }
+        if event.U > self.last_update_id + 1 {
+            return Ok(DepthUpdate::Gap);
+        }
+        update_levels(&mut self.bids, &event.b)?;
+        update_levels(&mut self.asks, &event.a)?;
+        self.last_update_id = event.u;
+        Ok(DepthUpdate::Applied)
+    }
+
+    /// Highest bid, as (price, quantity).
+    pub fn best_bid(&self) -> Option<(Decimal, Decimal)> {
+        self.bids
+            .last_key_value()
+            .map(|(price, quantity)| (*price, *quantity))
+    }
+
+    /// Lowest ask, as (price, quantity).
+    pub fn best_ask(&self) -> Option<(Decimal, Decimal)> {
+        self.asks
+            .first_key_value()
+            .map(|(price, quantity)| (*price, *quantity))
+    }
+
+    /// Bids, as (price, quantity), from the highest price down.
+    pub fn bids(&self) -> impl Iterator<Item = (&Decimal, &Decimal)> {
+        self.bids.iter().rev()
+    }
+
+    /// Asks, as (price, quantity), from the lowest price up.
+    pub fn asks(&self) -> impl Iterator<Item = (&Decimal, &Decimal)> {
+        self.asks.iter()
+    }
+
+    pub fn spread(&self) -> Option<Decimal> {
+        Some(self.best_ask()?.0 - self.best_bid()?.0)
+    }
+}
+
+enum SyncState {
+    /// Events received while awaiting a snapshot.
+    Buffering(Vec<DiffDepthStream>),
+    Synced(OrderBook),
+}
+
+/// Builds and maintains an order book from diff depth events and depth
+/// snapshots, following the procedure described by Binance:
+/// https://developers.binance.com/docs/binance-spot-api-docs/web-socket-streams#how-to-manage-a-local-order-book-correctly
+///
+/// Events are buffered until a snapshot, which is at least as recent as
+/// the first buffered event, has been applied. Events already included
+/// in the snapshot are discarded. Once a gap is detected the book is
+/// discarded and events are buffered again, awaiting a new snapshot.
+pub struct OrderBookSync {
+    symbol: String,
+    state: SyncState,
+}
+
+impl OrderBookSync {
+    pub fn new(symbol: &str) -> Self {
+        Self {
+            symbol: symbol.to_uppercase(),
+            state: SyncState::Buffering(Vec::new()),
+        }
+    }
+
+    /// Discards the book (e.g. after reconnecting), awaiting a new snapshot.
+    pub fn reset(&mut self) {
+        self.state = SyncState::Buffering(Vec::new());
+    }
+
+    /// The order book, `None` while awaiting a snapshot.
+    pub fn book(&self) -> Option<&OrderBook> {
+        match &self.state {
+            SyncState::Synced(book) => Some(book),
+            SyncState::Buffering(_) => None,
+        }
+    }
+
+    /// Whether a snapshot should be retrieved, i.e. whether the book is not
+    /// synchronised while events to apply to the snapshot have been received.
+    pub fn needs_snapshot(&self) -> bool {
+        matches!(&self.state, SyncState::Buffering(buffer) if !buffer.is_empty())
+    }
+
+    /// Applies `event` to the book, or buffers it while awaiting a snapshot.
+    pub fn push(&mut self, event: DiffDepthStream) -> Result<DepthUpdate, Error> {
+        match &mut self.state {
+            SyncState::Buffering(buffer) => {
+                buffer.push(event);
+                Ok(DepthUpdate::Stale)
+            }
+            SyncState::Synced(book) => {
+                let update = book.apply(&event)?;
+                if update == DepthUpdate::Gap {
+                    self.state = SyncState::Buffering(vec![event]);
+                }
+                Ok(update)
+            }
+        }
+    }
+
+    /// Sets the book to `snapshot` and applies the buffered events. Returns
+    /// `false` if the snapshot is older than the first buffered event, or
+    /// the buffered events do not follow the snapshot, in which case a
+    /// new snapshot should be retrieved.
+    pub fn apply_snapshot(&mut self, snapshot: &PartialDepthStream) -> Result<bool, Error> {
+        let SyncState::Buffering(buffer) = &mut self.state else {
+            return Ok(true);
+        };
+        if buffer
+            .first()
+            .is_none_or(|first| snapshot.last_update_id < first.U)
+        {
+            return Ok(false);
+        }
+
+        let mut book = OrderBook::from_snapshot(&self.symbol, snapshot)?;
+        for event in buffer.iter() {
+            if book.apply(event)? == DepthUpdate::Gap {
+                // Events older than the snapshot are no longer of use.
+                buffer.retain(|event| event.u > snapshot.last_update_id);
+                return Ok(false);
+            }
+        }
+        self.state = SyncState::Synced(book);
+        Ok(true)
+    }
+}
+
+/// Resets `sync` once the connection is lost, since events may be missed
+/// until it is re-established. Returns the event of `item`, if any.
+fn event_or_reset(
+    sync: &mut OrderBookSync,
+    item: StreamItem<DiffDepthStream>,
+) -> Option<DiffDepthStream> {
+    match item {
+        StreamItem::Event(event) => Some(event),
+        StreamItem::Connection(ConnectionEvent::Disconnected(_)) => {
+            sync.reset();
+            None
+        }
+        StreamItem::Connection(_) => None,
+    }
+}
+
+/// Maintains the order book of `symbol` on the current tokio runtime, until
+/// either `cancel` is cancelled or the stream ends, calling `on_update` after
+/// every update applied. Snapshots of up to `limit` levels are retrieved
+/// using `rest`, whenever the book has to be (re)synchronised, including
+/// after a lost connection has been re-established. Fails if the book is
+/// not synchronised after several snapshots.
+pub async fn maintain_order_book<F>(
+    symbol: &str,
+    speed: &UpdateSpeed,
+    limit: u16,
+    rest: &RestClient,
+    options: ReconnectOptions,
+    cancel: CancellationToken,
+    mut on_update: F,
+) -> Result<(), Error>
+where
+    F: FnMut(&OrderBook),
+{
+    let mut events = subscribe(
+        MarketStream::diff_depth(symbol, speed),
+        options,
+        cancel.clone(),
+    );
+    let mut sync = OrderBookSync::new(symbol);
+
+    while let Some(item) = events.recv().await {
+        let Some(event) = event_or_reset(&mut sync, item?) else {
+            continue;
+        };
+        if sync.push(event)? == DepthUpdate::Applied
+            && let Some(book) = sync.book()
+        {
+            on_update(book);
+        }
+
+        // Snapshots are retrieved while the stream continues to buffer events.
+        let mut attempt = 0;
+        while sync.needs_snapshot() {
+            if attempt > 0 {
+                if attempt >= SNAPSHOT_MAX_ATTEMPTS {
+                    return Err(Error::Other(format!(
+                        "Unable to synchronise the order book of {symbol} after {attempt} snapshots"
+                    )));
+                }
+                let delay =
+                    SNAPSHOT_INITIAL_BACKOFF.saturating_mul(2u32.saturating_pow(attempt - 1));
+                tokio::select! {
+                    _ = cancel.cancelled() => return Ok(()),
+                    _ = tokio::time::sleep(delay) => {}
+                }
+                // The snapshot may only be older than the first event if it
+                // was retrieved before the event was published, hence the
+                // events received in the meantime are buffered before retrying.
+                while let Some(item) = events.try_recv() {
+                    if let Some(event) = event_or_reset(&mut sync, item?) {
+                        sync.push(event)?;
+                    }
+                }
+                if !sync.needs_snapshot() {
+                    break;
+                }
+            }
+            attempt += 1;
+
+            let snapshot = rest.depth(symbol, limit).await?;
+            if sync.apply_snapshot(&snapshot)? {
+                if let Some(book) = sync.book() {
+                    on_update(book);
+                }
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels(levels: &[(&str, &str)]) -> Vec<[String; 2]> {
+        levels
+            .iter()
+            .map(|(price, quantity)| [price.to_string(), quantity.to_string()])
+            .collect()
+    }
+
+    fn decimal(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    fn snapshot(last_update_id: u64) -> PartialDepthStream {
+        PartialDepthStream {
+            last_update_id,
+            bids: levels(&[("0.0024", "10"), ("0.0023", "100")]),
+            asks: levels(&[("0.0026", "100"), ("0.0027", "5")]),
+        }
+    }
+
+    fn event(
+        first: u64,
+        last: u64,
+        bids: &[(&str, &str)],
+        asks: &[(&str, &str)],
+    ) -> DiffDepthStream {
+        DiffDepthStream {
+            e: "depthUpdate".to_string(),
+            E: 1748736000000,
+            s: "BNBBTC".to_string(),
+            U: first,
+            u: last,
+            b: levels(bids),
+            a: levels(asks),
+        }
+    }
+
+    #[test]
+    fn apply_stale_gap_and_following_events() {
+        let mut book = OrderBook::from_snapshot("bnbbtc", &snapshot(160)).unwrap();
+        assert_eq!(book.symbol, "BNBBTC");
+
+        // Only contains updates up to and including the snapshot.
+        let stale = event(157, 160, &[("0.0024", "0")], &[]);
+        assert_eq!(book.apply(&stale).unwrap(), DepthUpdate::Stale);
+        assert_eq!(book.best_bid(), Some((decimal("0.0024"), decimal("10"))));
+
+        // Straddles the snapshot, which is allowed.
+        let applied = event(
+            158,
+            162,
+            &[("0.0024", "0"), ("0.0025", "3")],
+            &[("0.0027", "0")],
+        );
+        assert_eq!(book.apply(&applied).unwrap(), DepthUpdate::Applied);
+        assert_eq!(book.last_update_id, 162);
+        assert_eq!(book.best_bid(), Some((decimal("0.0025"), decimal("3"))));
+        assert_eq!(book.spread(), Some(decimal("0.0001")));
+        let bids: Vec<Decimal> = book.bids().map(|(price, _)| *price).collect();
+        assert_eq!(bids, vec![decimal("0.0025"), decimal("0.0023")]);
+        let asks: Vec<Decimal> = book.asks().map(|(price, _)| *price).collect();
+        assert_eq!(asks, vec![decimal("0.0026")]);
+
+        let applied = event(163, 163, &[], &[("0.0026", "50")]);
+        assert_eq!(book.apply(&applied).unwrap(), DepthUpdate::Applied);
+        assert_eq!(book.best_ask(), Some((decimal("0.0026"), decimal("50"))));
+
+        // Updates 164 up to and including 165 are missing.
+        let gap = event(166, 170, &[], &[]);
+        assert_eq!(book.apply(&gap).unwrap(), DepthUpdate::Gap);
+        assert_eq!(book.last_update_id, 163);
+    }
+
+    #[test]
+    fn apply_snapshot_to_buffered_events() {
+        let mut sync = OrderBookSync::new("BNBBTC");
+        assert!(!sync.needs_snapshot());
+        sync.push(event(157, 160, &[], &[])).unwrap();
+        sync.push(event(161, 165, &[("0.0023", "0")], &[])).unwrap();
+        assert!(sync.needs_snapshot());
+        assert!(sync.book().is_none());
+
+        // Older than the first buffered event.
+        assert!(!sync.apply_snapshot(&snapshot(150)).unwrap());
+        assert!(sync.needs_snapshot());
+
+        assert!(sync.apply_snapshot(&snapshot(160)).unwrap());
+        assert!(!sync.needs_snapshot());
+        let book = sync.book().unwrap();
+        assert_eq!(book.last_update_id, 165);
+        assert_eq!(book.bids().count(), 1);
+
+        assert_eq!(
+            sync.push(event(166, 167, &[], &[])).unwrap(),
+            DepthUpdate::Applied
+        );
+    }
+
+    #[test]
+    fn reset_once_disconnected() {
+        let mut sync = OrderBookSync::new("BNBBTC");
+        sync.push(event(157, 160, &[], &[])).unwrap();
+        assert!(sync.apply_snapshot(&snapshot(160)).unwrap());
+
+        let connected = StreamItem::Connection(ConnectionEvent::Connected);
+        assert!(event_or_reset(&mut sync, connected).is_none());
+        assert!(sync.book().is_some());
+
+        let disconnected = StreamItem::Connection(ConnectionEvent::Disconnected(
+            "Connection closed".to_string(),
+        ));
+        assert!(event_or_reset(&mut sync, disconnected).is_none());
+        assert!(sync.book().is_none());
+        assert!(!sync.needs_snapshot());
+
+        // Events received after reconnecting are buffered for a new snapshot.
+        let event = event_or_reset(&mut sync, StreamItem::Event(event(170, 172, &[], &[])));
+        assert_eq!(sync.push(event.unwrap()).unwrap(), DepthUpdate::Stale);
+        assert!(sync.needs_snapshot());
+    }
+
+    #[test]
+    fn resynchronise_after_gap() {
+        let mut sync = OrderBookSync::new("BNBBTC");
+        sync.push(event(157, 160, &[], &[])).unwrap();
+        sync.push(event(170, 172, &[], &[])).unwrap();
+
+        // Updates 161 up to and including 169 are missing from the buffer.
+        assert!(!sync.apply_snapshot(&snapshot(160)).unwrap());
+        assert!(sync.needs_snapshot());
+        assert!(sync.apply_snapshot(&snapshot(171)).unwrap());
+        assert_eq!(sync.book().unwrap().last_update_id, 172);
+
+        // A gap discards the book, buffering the event awaiting a snapshot.
+        assert_eq!(
+            sync.push(event(180, 181, &[], &[])).unwrap(),
+            DepthUpdate::Gap
+        );
+        assert!(sync.book().is_none());
+        assert!(sync.needs_snapshot());
+        assert!(sync.apply_snapshot(&snapshot(180)).unwrap());
+        assert_eq!(sync.book().unwrap().last_update_id, 181);
+
+        sync.reset();
+        assert!(sync.book().is_none());
+        assert!(!sync.needs_snapshot());
+    }
+
+    #[test]
+    fn invalid_levels() {
+        let mut book = OrderBook::from_snapshot("BNBBTC", &snapshot(160)).unwrap();
+        let invalid = event(161, 161, &[("0.0024", "ten")], &[]);
+        assert!(book.apply(&invalid).is_err());
+    }
+}
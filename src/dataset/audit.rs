@@ -0,0 +1,474 @@
+use chrono::{DateTime, NaiveDate};
+use std::collections::{BTreeSet, HashSet};
+
+use crate::binance::historical::{
+    ArchiveFrequency, Dataset, DownloadOptions, DownloadReport, HistoricalClient, Market,
+};
+use crate::binance::stream::KlineInterval;
+use crate::errors::Error;
+use crate::models::HistoricalKlineEvent;
+
+/// Range of consecutive klines missing from a series.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gap {
+    /// Open time of the first missing kline.
+    pub from: i64,
+    /// Open time of the last missing kline.
+    pub to: i64,
+    /// Number of missing klines.
+    pub missing: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OhlcViolation {
+    /// High is below the open or close price.
+    HighBelowBody,
+    /// Low is above the open or close price.
+    LowAboveBody,
+    HighBelowLow,
+    NonPositivePrice,
+}
+
+/// Issues identified in a kline series. All times are kline open
+/// times, in milliseconds.
+#[derive(Debug, Clone, Default)]
+pub struct AuditReport {
+    pub gaps: Vec<Gap>,
+    pub duplicates: Vec<i64>,
+    /// Position in the series, and open time, of klines opened
+    /// before their predecessor.
+    pub out_of_order: Vec<(usize, i64)>,
+    pub ohlc_violations: Vec<(i64, OhlcViolation)>,
+}
+
+impl AuditReport {
+    pub fn is_clean(&self) -> bool {
+        self.gaps.is_empty()
+            && self.duplicates.is_empty()
+            && self.out_of_order.is_empty()
+            && self.ohlc_violations.is_empty()
+    }
+
+    /// Days (UTC) containing a gap or an OHLC violation.
+    pub fn affected_days(&self, interval: &KlineInterval) -> BTreeSet<NaiveDate> {
+        let mut days = BTreeSet::new();
+        for gap in &self.gaps {
+            let mut t = gap.from;
+            while t <= gap.to {
+                days.extend(open_date(t));
+                match interval.next_open_time(t) {
+                    Some(next) => t = next,
+                    None => break,
+                }
+            }
+        }
+        days.extend(
+            self.ohlc_violations
+                .iter()
+                .filter_map(|(t, _)| open_date(*t)),
+        );
+        days
+    }
+}
+
+/// How the issues identified by an audit are repaired. Regardless
+/// of the policy, repaired series are sorted and deduplicated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepairPolicy {
+    /// Gaps, and klines violating OHLC consistency, are replaced by flat
+    /// klines at the previous close price without any volume.
+    ForwardFill,
+    /// Klines violating OHLC consistency are removed, gaps remain.
+    Drop,
+}
+
+fn open_date(t: i64) -> Option<NaiveDate> {
+    DateTime::from_timestamp_millis(t).map(|datetime| datetime.date_naive())
+}
+
+fn ohlc_violation(kline: &HistoricalKlineEvent) -> Option<OhlcViolation> {
+    if [kline.o, kline.h, kline.l, kline.c]
+        .iter()
+        .any(|price| price.is_nan() || *price <= 0.0)
+    {
+        return Some(OhlcViolation::NonPositivePrice);
+    }
+    if kline.h < kline.l {
+        return Some(OhlcViolation::HighBelowLow);
+    }
+    if kline.h < kline.o.max(kline.c) {
+        return Some(OhlcViolation::HighBelowBody);
+    }
+    if kline.l > kline.o.min(kline.c) {
+        return Some(OhlcViolation::LowAboveBody);
+    }
+    None
+}
+
+/// Identifies gaps, duplicates, out of order klines and OHLC violations
+/// in a series of klines of the specified interval.
+pub fn audit(klines: &[HistoricalKlineEvent], interval: &KlineInterval) -> AuditReport {
+    let mut report = AuditReport::default();
+
+    let mut seen = HashSet::with_capacity(klines.len());
+    for (index, kline) in klines.iter().enumerate() {
+        if index > 0 && kline.t < klines[index - 1].t {
+            report.out_of_order.push((index, kline.t));
+        }
+        if !seen.insert(kline.t) {
+            report.duplicates.push(kline.t);
+        }
+        if let Some(violation) = ohlc_violation(kline) {
+            report.ohlc_violations.push((kline.t, violation));
+        }
+    }
+
+    let open_times: BTreeSet<i64> = seen.into_iter().collect();
+    for (previous, current) in open_times.iter().zip(open_times.iter().skip(1)) {
+        if let Some(gap) = find_gap(*previous, *current, interval) {
+            report.gaps.push(gap);
+        }
+    }
+
+    report
+}
+
+/// Gap between two consecutive open times, if any.
+fn find_gap(previous: i64, current: i64, interval: &KlineInterval) -> Option<Gap> {
+    let from = interval.next_open_time(previous)?;
+    if from >= current {
+        return None;
+    }
+
+    let mut to = from;
+    let mut missing = 1;
+    while let Some(next) = interval.next_open_time(to) {
+        if next >= current {
+            break;
+        }
+        to = next;
+        missing += 1;
+    }
+    Some(Gap { from, to, missing })
+}
+
+/// Flat kline, opened at `t`, at the close price of `previous`.
+pub(crate) fn forward_filled(
+    previous: &HistoricalKlineEvent,
+    t: i64,
+    close_time: i64,
+) -> HistoricalKlineEvent {
+    HistoricalKlineEvent {
+        t,
+        o: previous.c,
+        h: previous.c,
+        l: previous.c,
+        c: previous.c,
+        v: 0.0,
+        T: close_time,
+        q: 0.0,
+        n: 0,
+        V: 0.0,
+        Q: 0.0,
+        B: "0".to_string(),
+    }
+}
+
+/// Repairs a series of klines according to `policy`.
+pub fn repair(
+    mut klines: Vec<HistoricalKlineEvent>,
+    interval: &KlineInterval,
+    policy: RepairPolicy,
+) -> Vec<HistoricalKlineEvent> {
+    klines.sort();
+    klines.dedup();
+
+    let mut repaired: Vec<HistoricalKlineEvent> = Vec::with_capacity(klines.len());
+    for kline in klines {
+        let violation = ohlc_violation(&kline).is_some();
+        match (policy, repaired.last()) {
+            (RepairPolicy::Drop, _) if violation => continue,
+            (RepairPolicy::ForwardFill, None) if violation => continue,
+            (RepairPolicy::ForwardFill, Some(previous)) => {
+                let previous = previous.clone();
+                let mut t = previous.t;
+                while let Some(next) = interval.next_open_time(t) {
+                    if next >= kline.t {
+                        break;
+                    }
+                    let close_time = interval.next_open_time(next).unwrap_or(next + 1) - 1;
+                    repaired.push(forward_filled(&previous, next, close_time));
+                    t = next;
+                }
+                if violation {
+                    let last = repaired.last().unwrap_or(&previous).clone();
+                    repaired.push(forward_filled(&last, kline.t, kline.T));
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        repaired.push(kline);
+    }
+    repaired
+}
+
+/// Downloads the daily archives of the days affected by the issues in
+/// `report` again, and replaces the klines of the downloaded days in the
+/// series. Saved archives, and the klines, of days that could not be
+/// downloaded (e.g. unpublished days) are kept.
+pub async fn redownload_affected_days(
+    mut klines: Vec<HistoricalKlineEvent>,
+    report: &AuditReport,
+    client: &HistoricalClient,
+    market: &Market,
+    symbol: &str,
+    interval: &KlineInterval,
+    options: &DownloadOptions,
+) -> Result<(Vec<HistoricalKlineEvent>, DownloadReport), Error> {
+    let frequency = ArchiveFrequency::Daily;
+    let dataset = Dataset::Klines(interval.to_string());
+    let days = report.affected_days(interval);
+    let options = DownloadOptions {
+        overwrite: true,
+        ..options.clone()
+    };
+
+    let download_report = client
+        .retrieve_and_save_dataset_range(
+            days.iter().copied(),
+            &frequency,
+            market,
+            symbol,
+            &dataset,
+            &options,
+        )
+        .await?;
+    let downloaded: BTreeSet<NaiveDate> = download_report.downloaded.iter().copied().collect();
+    let replacements = client
+        .load_klines(
+            downloaded.iter().copied(),
+            &frequency,
+            market,
+            symbol,
+            &interval.to_string(),
+        )
+        .await?;
+
+    klines.retain(|kline| open_date(kline.t).is_none_or(|day| !downloaded.contains(&day)));
+    klines.extend(replacements);
+    klines.sort();
+    klines.dedup();
+    Ok((klines, download_report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance::historical::tests::{
+        StubFile, daily_archive_name, published, serve_files, stub_client, temp_dir, zip_archive,
+    };
+
+    const MINUTE: i64 = 60_000;
+    // 1 June 2025 00:00 UTC
+    const START: i64 = 1748736000000;
+
+    fn kline(minute: i64, o: f64, h: f64, l: f64, c: f64) -> HistoricalKlineEvent {
+        let t = START + minute * MINUTE;
+        HistoricalKlineEvent {
+            t,
+            o,
+            h,
+            l,
+            c,
+            v: 1.0,
+            T: t + MINUTE - 1,
+            q: c,
+            n: 1,
+            V: 0.5,
+            Q: c / 2.0,
+            B: "0".to_string(),
+        }
+    }
+
+    fn flat(minute: i64, price: f64) -> HistoricalKlineEvent {
+        kline(minute, price, price, price, price)
+    }
+
+    #[test]
+    fn find_gap_between_open_times() {
+        let interval = KlineInterval::OneMinute;
+        assert_eq!(find_gap(START, START + MINUTE, &interval), None);
+        assert_eq!(
+            find_gap(START, START + 4 * MINUTE, &interval),
+            Some(Gap {
+                from: START + MINUTE,
+                to: START + 3 * MINUTE,
+                missing: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn audit_identifies_every_issue() {
+        let klines = vec![
+            flat(0, 10.0),
+            flat(1, 10.0),
+            flat(4, 10.0),
+            flat(3, 10.0),
+            flat(3, 10.0),
+            kline(5, 10.0, 9.0, 8.0, 10.0),
+        ];
+        let report = audit(&klines, &KlineInterval::OneMinute);
+        assert!(!report.is_clean());
+        assert_eq!(
+            report.gaps,
+            vec![Gap {
+                from: START + 2 * MINUTE,
+                to: START + 2 * MINUTE,
+                missing: 1,
+            }]
+        );
+        assert_eq!(report.duplicates, vec![START + 3 * MINUTE]);
+        assert_eq!(report.out_of_order, vec![(3, START + 3 * MINUTE)]);
+        assert_eq!(
+            report.ohlc_violations,
+            vec![(START + 5 * MINUTE, OhlcViolation::HighBelowBody)]
+        );
+        assert_eq!(
+            report.affected_days(&KlineInterval::OneMinute),
+            BTreeSet::from([NaiveDate::from_ymd_opt(2025, 6, 1).unwrap()])
+        );
+    }
+
+    #[test]
+    fn audit_of_clean_series() {
+        let klines: Vec<HistoricalKlineEvent> = (0..10).map(|minute| flat(minute, 10.0)).collect();
+        assert!(audit(&klines, &KlineInterval::OneMinute).is_clean());
+    }
+
+    #[test]
+    fn repair_forward_fills_gaps_and_violations() {
+        let klines = vec![
+            kline(0, 10.0, 12.0, 9.0, 11.0),
+            kline(3, 11.0, 13.0, 10.0, 12.0),
+            kline(2, 11.0, 10.0, 12.0, 11.0),
+            kline(0, 10.0, 12.0, 9.0, 11.0),
+        ];
+        let interval = KlineInterval::OneMinute;
+        let repaired = repair(klines, &interval, RepairPolicy::ForwardFill);
+
+        let open_times: Vec<i64> = repaired.iter().map(|kline| kline.t).collect();
+        assert_eq!(
+            open_times,
+            (0..4)
+                .map(|minute| START + minute * MINUTE)
+                .collect::<Vec<_>>()
+        );
+        // The missing kline, and the kline with its high below its low,
+        // are replaced by flat klines at the previous close.
+        for filled in &repaired[1..3] {
+            assert_eq!(
+                (filled.o, filled.h, filled.l, filled.c),
+                (11.0, 11.0, 11.0, 11.0)
+            );
+            assert_eq!(filled.v, 0.0);
+            assert_eq!(filled.T, filled.t + MINUTE - 1);
+        }
+        assert_eq!(repaired[3].c, 12.0);
+        assert!(audit(&repaired, &interval).is_clean());
+    }
+
+    #[test]
+    fn repair_drops_violations() {
+        let klines = vec![flat(0, 10.0), kline(1, 10.0, 9.0, 8.0, 10.0), flat(2, 10.0)];
+        let repaired = repair(klines, &KlineInterval::OneMinute, RepairPolicy::Drop);
+        let open_times: Vec<i64> = repaired.iter().map(|kline| kline.t).collect();
+        assert_eq!(open_times, vec![START, START + 2 * MINUTE]);
+    }
+
+    /// Daily kline archive containing the specified klines.
+    fn daily_archive(day: NaiveDate, klines: &[HistoricalKlineEvent]) -> Vec<StubFile> {
+        let dataset = Dataset::Klines(KlineInterval::OneMinute.to_string());
+        let name = daily_archive_name("BTCUSDT", &dataset, day);
+        let rows: Vec<String> = klines
+            .iter()
+            .map(|k| {
+                format!(
+                    "{},{},{},{},{},{},{},{},{},{},{},{}",
+                    k.t, k.o, k.h, k.l, k.c, k.v, k.T, k.q, k.n, k.V, k.Q, k.B
+                )
+            })
+            .collect();
+        let archive = zip_archive(&name.replace(".zip", ".csv"), &rows.join("\n"));
+        published(&name, &archive)
+    }
+
+    #[tokio::test]
+    async fn redownload_replaces_downloaded_days_only() {
+        let root = temp_dir("redownload");
+        let interval = KlineInterval::OneMinute;
+        let first = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let second = NaiveDate::from_ymd_opt(2025, 6, 2).unwrap();
+        let violation = kline(0, 10.0, 9.0, 8.0, 10.0);
+        let next_day_violation = kline(24 * 60, 10.0, 9.0, 8.0, 10.0);
+        let options = DownloadOptions {
+            max_retries: 0,
+            ..Default::default()
+        };
+
+        let mut files = daily_archive(first, std::slice::from_ref(&violation));
+        files.extend(daily_archive(
+            second,
+            std::slice::from_ref(&next_day_violation),
+        ));
+        let client = stub_client(serve_files(files), &root);
+        let klines = vec![violation, next_day_violation.clone()];
+        let report = audit(&klines, &interval);
+        assert_eq!(
+            report.affected_days(&interval),
+            BTreeSet::from([first, second])
+        );
+        client
+            .retrieve_and_save_dataset_range(
+                [first, second],
+                &ArchiveFrequency::Daily,
+                &Market::Spot,
+                "BTCUSDT",
+                &Dataset::Klines(interval.to_string()),
+                &options,
+            )
+            .await
+            .unwrap();
+
+        // Only the first day has been corrected, the second is not published.
+        let corrected = flat(0, 10.0);
+        let client = stub_client(
+            serve_files(daily_archive(first, std::slice::from_ref(&corrected))),
+            &root,
+        );
+        let (repaired, download_report) = redownload_affected_days(
+            klines,
+            &report,
+            &client,
+            &Market::Spot,
+            "BTCUSDT",
+            &interval,
+            &options,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(download_report.downloaded, vec![first]);
+        assert_eq!(download_report.missing, vec![second]);
+        assert_eq!(repaired, vec![corrected, next_day_violation]);
+        assert!(client.is_saved(
+            &ArchiveFrequency::Daily,
+            &Market::Spot,
+            "BTCUSDT",
+            &Dataset::Klines(interval.to_string()),
+            &second,
+        ));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,351 @@
+use chrono::{Days, NaiveDate, NaiveTime, Utc};
+
+use crate::binance::historical::{
+    ArchiveFrequency, Dataset, DownloadOptions, DownloadReport, HistoricalClient, HistoricalConfig,
+    Market, fall_back_to_daily_archives, plan_archives,
+};
+use crate::binance::rest::RestClient;
+use crate::binance::stream::KlineInterval;
+use crate::dataset::audit::{AuditReport, RepairPolicy, audit, repair};
+use crate::dataset::resample::{PartialBuckets, resample};
+use crate::errors::Error;
+use crate::models::{HistoricalKlineEvent, KlineEvent};
+
+/// Describes a kline series by symbol, interval and date range, which is
+/// downloaded (if required) and loaded in a single call.
+///
+/// ```ignore
+/// let series = KlineDataset::new("ETHUSDT", KlineInterval::OneHour)
+///     .range(start, end)
+///     .load()
+///     .await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct KlineDataset {
+    symbol: String,
+    interval: KlineInterval,
+    market: Market,
+    start: NaiveDate,
+    end: NaiveDate,
+    download: bool,
+    client: Option<HistoricalClient>,
+    options: DownloadOptions,
+    backfill: Option<RestClient>,
+}
+
+impl KlineDataset {
+    /// Defaults to the spot market, up to and including today.
+    pub fn new(symbol: &str, interval: KlineInterval) -> Self {
+        let today = Utc::now().date_naive();
+        Self {
+            symbol: symbol.to_uppercase(),
+            interval,
+            market: Market::Spot,
+            start: today,
+            end: today,
+            download: true,
+            client: None,
+            options: DownloadOptions::default(),
+            backfill: None,
+        }
+    }
+
+    pub(crate) fn symbol(mut self, symbol: &str) -> Self {
+        self.symbol = symbol.to_uppercase();
+        self
+    }
+
+    pub(crate) fn has_client(&self) -> bool {
+        self.client.is_some()
+    }
+
+    pub fn market(mut self, market: Market) -> Self {
+        self.market = market;
+        self
+    }
+
+    /// Range of dates (inclusive) the series covers.
+    pub fn range(mut self, start: NaiveDate, end: NaiveDate) -> Self {
+        self.start = start;
+        self.end = end;
+        self
+    }
+
+    /// Whether missing archives are downloaded before loading, defaults to `true`.
+    pub fn download(mut self, download: bool) -> Self {
+        self.download = download;
+        self
+    }
+
+    /// Client used to download and load archives, defaults to a client
+    /// created using `HistoricalConfig::default()`.
+    pub fn client(mut self, client: HistoricalClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    pub fn download_options(mut self, options: DownloadOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Completes the series, beyond the most recent archive, using klines
+    /// retrieved from the REST API, see `KlineSeries::backfill`.
+    pub fn backfill(mut self, client: RestClient) -> Self {
+        self.backfill = Some(client);
+        self
+    }
+
+    /// Downloads missing archives (unless disabled), after which the
+    /// klines within the requested range are loaded, deduplicated and sorted.
+    pub async fn load(self) -> Result<KlineSeries, Error> {
+        Ok(self.load_with_reports().await?.0)
+    }
+
+    /// Similar to `load`, however also returns the outcome of the downloads.
+    pub async fn load_with_reports(
+        self,
+    ) -> Result<(KlineSeries, Vec<(ArchiveFrequency, DownloadReport)>), Error> {
+        let client = match self.client {
+            Some(client) => client,
+            None => HistoricalClient::new(HistoricalConfig::default())?,
+        };
+        let interval = self.interval.to_string();
+        let dataset = Dataset::Klines(interval.clone());
+
+        let reports = if self.download {
+            client
+                .retrieve_and_save_historical_data_up_to_date(
+                    self.start,
+                    self.end,
+                    &self.market,
+                    &self.symbol,
+                    &dataset,
+                    &self.options,
+                )
+                .await?
+        } else {
+            Vec::new()
+        };
+
+        // Months without a saved monthly archive are loaded from the daily
+        // archives, e.g. those stored while the monthly one was unpublished.
+        let plan = plan_archives(self.start, self.end, Utc::now().date_naive());
+        let plan = fall_back_to_daily_archives(plan, self.start, self.end, |month| {
+            !client.is_saved(
+                &ArchiveFrequency::Monthly,
+                &self.market,
+                &self.symbol,
+                &dataset,
+                month,
+            )
+        });
+        let mut klines = Vec::new();
+        for frequency in [ArchiveFrequency::Monthly, ArchiveFrequency::Daily] {
+            let dates: Vec<NaiveDate> = plan
+                .iter()
+                .filter(|(f, _)| f == &frequency)
+                .map(|(_, date)| *date)
+                .collect();
+            klines.extend(
+                client
+                    .load_klines(dates, &frequency, &self.market, &self.symbol, &interval)
+                    .await?,
+            );
+        }
+
+        // Monthly archives cover whole months, hence klines outside of
+        // the requested range are removed.
+        let window_start = self
+            .start
+            .and_time(NaiveTime::MIN)
+            .and_utc()
+            .timestamp_millis();
+        let window_end = self
+            .end
+            .checked_add_days(Days::new(1))
+            .map(|date| date.and_time(NaiveTime::MIN).and_utc().timestamp_millis());
+        klines
+            .retain(|kline| kline.t >= window_start && window_end.is_none_or(|end| kline.t < end));
+        klines.sort();
+        klines.dedup();
+
+        let mut series = KlineSeries {
+            symbol: self.symbol,
+            interval: self.interval,
+            market: self.market,
+            klines,
+        };
+        if let Some(rest) = &self.backfill {
+            series.backfill(rest, window_start, window_end).await?;
+        }
+        Ok((series, reports))
+    }
+}
+
+/// Sorted, deduplicated klines of a single symbol and interval.
+#[derive(Debug, Clone)]
+pub struct KlineSeries {
+    pub symbol: String,
+    pub interval: KlineInterval,
+    pub market: Market,
+    pub klines: Vec<HistoricalKlineEvent>,
+}
+
+impl KlineSeries {
+    pub fn len(&self) -> usize {
+        self.klines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.klines.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &HistoricalKlineEvent> {
+        self.klines.iter()
+    }
+
+    /// Identifies gaps, duplicates, out of order klines and OHLC violations.
+    pub fn audit(&self) -> AuditReport {
+        audit(&self.klines, &self.interval)
+    }
+
+    pub fn repair(&mut self, policy: RepairPolicy) {
+        self.klines = repair(std::mem::take(&mut self.klines), &self.interval, policy);
+    }
+
+    /// Resamples the series to a larger interval, see `resample`.
+    pub fn resample(
+        &self,
+        interval: KlineInterval,
+        partial: PartialBuckets,
+    ) -> Result<KlineSeries, Error> {
+        Ok(KlineSeries {
+            symbol: self.symbol.clone(),
+            interval,
+            market: self.market,
+            klines: resample(&self.klines, &self.interval, &interval, partial)?,
+        })
+    }
+
+    /// Appends the closed klines opened at or after `start`, and after the
+    /// last kline of the series, (and before `end`, in milliseconds),
+    /// retrieved from the REST API. Archives lag by at least a day, hence
+    /// this brings a series loaded from archives up to date, including a
+    /// series for which no archives exist yet. Returns the number of klines
+    /// appended.
+    pub async fn backfill(
+        &mut self,
+        client: &RestClient,
+        start: i64,
+        end: Option<i64>,
+    ) -> Result<usize, Error> {
+        if client.market() != self.market {
+            return Err(Error::Other(format!(
+                "Unable to backfill {} klines using a {} client",
+                self.market,
+                client.market()
+            )));
+        }
+        let start = match self.klines.last() {
+            Some(last) => match self.interval.next_open_time(last.t) {
+                Some(next) => next.max(start),
+                None => return Ok(0),
+            },
+            None => start,
+        };
+
+        let now = Utc::now().timestamp_millis();
+        let recent = client
+            .klines(&self.symbol, &self.interval, start, end)
+            .await?;
+        let before = self.klines.len();
+        self.klines
+            .extend(recent.into_iter().filter(|kline| kline.T < now));
+        Ok(self.klines.len() - before)
+    }
+
+    pub fn closes(&self) -> Vec<f64> {
+        self.klines.iter().map(|kline| kline.c).collect()
+    }
+
+    /// Klines in the form received by strategies, i.e. stream events.
+    pub fn events(&self) -> impl Iterator<Item = KlineEvent> + '_ {
+        self.klines.iter().cloned().map(KlineEvent::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance::historical::tests::{serve_files, stub_client, temp_dir};
+    use crate::binance::rest::RestConfig;
+
+    // 1 June 2025 00:00 UTC
+    const START: i64 = 1748736000000;
+    const HOUR: i64 = 3_600_000;
+
+    /// REST API responding with hourly klines opened at the given hours.
+    fn rest_client(hours: &[i64]) -> RestClient {
+        let rows: Vec<String> = hours
+            .iter()
+            .map(|hour| {
+                let t = START + hour * HOUR;
+                format!(
+                    "[{t},\"10.0\",\"11.0\",\"9.0\",\"10.5\",\"2.0\",{},\"21.0\",4,\"1.0\",\"10.5\",\"0\"]",
+                    t + HOUR - 1
+                )
+            })
+            .collect();
+        let body = format!("[{}]", rows.join(","));
+        let base_url = serve_files(vec![("klines".to_string(), "200 OK", body.into_bytes())]);
+        RestClient::new(RestConfig {
+            base_url: Some(base_url),
+            max_retries: 0,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn backfill_range_without_archives() {
+        let root = temp_dir("backfill");
+        let day = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let (series, reports) = KlineDataset::new("BTCUSDT", KlineInterval::OneHour)
+            .range(day, day)
+            .client(stub_client(serve_files(Vec::new()), &root))
+            .backfill(rest_client(&[0, 1, 2]))
+            .load_with_reports()
+            .await
+            .unwrap();
+
+        assert_eq!(reports[0].0, ArchiveFrequency::Monthly);
+        assert_eq!(reports[0].1.missing, vec![day]);
+        let open_times: Vec<i64> = series.klines.iter().map(|kline| kline.t).collect();
+        assert_eq!(open_times, vec![START, START + HOUR, START + 2 * HOUR]);
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn backfill_empty_series_of_same_market() {
+        let mut series = KlineSeries {
+            symbol: "BTCUSDT".to_string(),
+            interval: KlineInterval::OneHour,
+            market: Market::Spot,
+            klines: Vec::new(),
+        };
+        let appended = series
+            .backfill(&rest_client(&[0, 1]), START, Some(START + 2 * HOUR))
+            .await
+            .unwrap();
+        assert_eq!(appended, 2);
+
+        let futures = RestClient::new(RestConfig {
+            market: Market::UsdMFutures,
+            base_url: Some(serve_files(Vec::new())),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(series.backfill(&futures, START, None).await.is_err());
+    }
+}
@@ -0,0 +1,4 @@
+pub mod audit;
+pub mod kline;
+pub mod panel;
+pub mod resample;
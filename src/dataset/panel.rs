@@ -0,0 +1,438 @@
+use chrono::NaiveDate;
+use std::collections::BTreeSet;
+
+use crate::binance::historical::{
+    ArchiveFrequency, DownloadOptions, DownloadReport, HistoricalClient, HistoricalConfig, Market,
+};
+use crate::binance::stream::KlineInterval;
+use crate::dataset::audit::forward_filled;
+use crate::dataset::kline::{KlineDataset, KlineSeries};
+use crate::errors::Error;
+use crate::models::HistoricalKlineEvent;
+
+/// Open times included in a panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Join {
+    /// Only open times at which every symbol has a kline.
+    Inner,
+    /// Open times at which any symbol has a kline.
+    Outer,
+}
+
+/// Handling of open times at which a symbol has no kline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fill {
+    /// Missing klines are left empty.
+    None,
+    /// Missing klines are replaced by a flat kline at the previous close,
+    /// for at most `limit` consecutive klines (unlimited if `None`).
+    Forward { limit: Option<usize> },
+}
+
+/// Describes klines of several symbols, of the same interval and date
+/// range, which are downloaded (if required), loaded and aligned on open time.
+///
+/// ```ignore
+/// let panel = PanelDataset::new(&["ETHUSDT", "BTCUSDT"], KlineInterval::OneHour)
+///     .range(start, end)
+///     .load()
+///     .await?;
+/// let (y, x) = panel.matched_closes("ETHUSDT", "BTCUSDT").unwrap();
+/// let residuals = compute_residuals(&y, &x);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PanelDataset {
+    symbols: Vec<String>,
+    /// Dataset loaded for every symbol, with the symbol swapped.
+    template: KlineDataset,
+    join: Join,
+    fill: Fill,
+}
+
+impl PanelDataset {
+    /// Defaults to those of `KlineDataset::new`, using an inner join without filling.
+    pub fn new(symbols: &[&str], interval: KlineInterval) -> Self {
+        Self {
+            symbols: symbols.iter().map(|symbol| symbol.to_uppercase()).collect(),
+            template: KlineDataset::new("", interval),
+            join: Join::Inner,
+            fill: Fill::None,
+        }
+    }
+
+    pub fn market(mut self, market: Market) -> Self {
+        self.template = self.template.market(market);
+        self
+    }
+
+    /// See `KlineDataset::range`.
+    pub fn range(mut self, start: NaiveDate, end: NaiveDate) -> Self {
+        self.template = self.template.range(start, end);
+        self
+    }
+
+    pub fn join(mut self, join: Join) -> Self {
+        self.join = join;
+        self
+    }
+
+    pub fn fill(mut self, fill: Fill) -> Self {
+        self.fill = fill;
+        self
+    }
+
+    /// See `KlineDataset::download`.
+    pub fn download(mut self, download: bool) -> Self {
+        self.template = self.template.download(download);
+        self
+    }
+
+    /// See `KlineDataset::client`.
+    pub fn client(mut self, client: HistoricalClient) -> Self {
+        self.template = self.template.client(client);
+        self
+    }
+
+    pub fn download_options(mut self, options: DownloadOptions) -> Self {
+        self.template = self.template.download_options(options);
+        self
+    }
+
+    /// Loads the series of every symbol, after which they are aligned.
+    pub async fn load(self) -> Result<KlinePanel, Error> {
+        Ok(self.load_with_reports().await?.0)
+    }
+
+    /// Similar to `load`, however also returns the outcome of the downloads
+    /// per symbol.
+    #[allow(clippy::type_complexity)]
+    pub async fn load_with_reports(
+        self,
+    ) -> Result<
+        (
+            KlinePanel,
+            Vec<(String, Vec<(ArchiveFrequency, DownloadReport)>)>,
+        ),
+        Error,
+    > {
+        // Every symbol is loaded using the same client.
+        let template = if self.template.has_client() {
+            self.template
+        } else {
+            self.template
+                .client(HistoricalClient::new(HistoricalConfig::default())?)
+        };
+
+        let mut series = Vec::with_capacity(self.symbols.len());
+        let mut reports = Vec::with_capacity(self.symbols.len());
+        for symbol in &self.symbols {
+            let (symbol_series, symbol_reports) =
+                template.clone().symbol(symbol).load_with_reports().await?;
+            series.push(symbol_series);
+            reports.push((symbol.clone(), symbol_reports));
+        }
+
+        let panel = KlinePanel::align(&series, self.join, self.fill)?;
+        Ok((panel, reports))
+    }
+}
+
+/// Klines of several symbols aligned on open time. Every symbol has one
+/// (possibly empty) entry per open time in `times`.
+#[derive(Debug, Clone)]
+pub struct KlinePanel {
+    pub interval: KlineInterval,
+    pub symbols: Vec<String>,
+    /// Open times, in milliseconds, in ascending order.
+    pub times: Vec<i64>,
+    /// Klines per symbol, in the order of `symbols`.
+    pub klines: Vec<Vec<Option<HistoricalKlineEvent>>>,
+}
+
+impl KlinePanel {
+    /// Aligns series of the same interval on open time.
+    pub fn align(series: &[KlineSeries], join: Join, fill: Fill) -> Result<Self, Error> {
+        let interval = match series.first() {
+            Some(first) => first.interval,
+            None => return Err(Error::Other("Panel requires at least one series".into())),
+        };
+        if let Some(other) = series.iter().find(|s| s.interval != interval) {
+            return Err(Error::Other(format!(
+                "Panel requires a single interval, {} is {} rather than {interval}",
+                other.symbol, other.interval
+            )));
+        }
+
+        let times: Vec<i64> = match join {
+            Join::Outer => series
+                .iter()
+                .flat_map(|s| s.iter().map(|kline| kline.t))
+                .collect::<BTreeSet<i64>>()
+                .into_iter()
+                .collect(),
+            Join::Inner => {
+                let mut times: BTreeSet<i64> = series[0].iter().map(|kline| kline.t).collect();
+                for s in &series[1..] {
+                    let other: BTreeSet<i64> = s.iter().map(|kline| kline.t).collect();
+                    times.retain(|t| other.contains(t));
+                }
+                times.into_iter().collect()
+            }
+        };
+
+        let klines = series
+            .iter()
+            .map(|s| align_series(&s.klines, &times, &interval, fill))
+            .collect();
+
+        Ok(Self {
+            interval,
+            symbols: series.iter().map(|s| s.symbol.clone()).collect(),
+            times,
+            klines,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.times.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.times.is_empty()
+    }
+
+    fn position(&self, symbol: &str) -> Option<usize> {
+        self.symbols
+            .iter()
+            .position(|s| s.eq_ignore_ascii_case(symbol))
+    }
+
+    /// Aligned klines of `symbol`, `None` if the symbol is not part of the panel.
+    pub fn series(&self, symbol: &str) -> Option<&[Option<HistoricalKlineEvent>]> {
+        self.position(symbol).map(|i| self.klines[i].as_slice())
+    }
+
+    /// Aligned close prices of `symbol`, empty where the symbol has no kline.
+    pub fn closes(&self, symbol: &str) -> Option<Vec<Option<f64>>> {
+        self.series(symbol).map(|klines| {
+            klines
+                .iter()
+                .map(|kline| kline.as_ref().map(|k| k.c))
+                .collect()
+        })
+    }
+
+    /// Close prices of `y` and `x` at the open times both have a kline,
+    /// e.g. for `compute_residuals(&y, &x)`.
+    pub fn matched_closes(&self, y: &str, x: &str) -> Option<(Vec<f64>, Vec<f64>)> {
+        let y = self.series(y)?;
+        let x = self.series(x)?;
+        Some(
+            y.iter()
+                .zip(x)
+                .filter_map(|(y, x)| Some((y.as_ref()?.c, x.as_ref()?.c)))
+                .unzip(),
+        )
+    }
+
+    /// Close prices per symbol, in the order of `symbols`, at the open
+    /// times every symbol has a kline.
+    pub fn complete_closes(&self) -> Vec<Vec<f64>> {
+        let mut closes = vec![Vec::new(); self.symbols.len()];
+        for row in 0..self.times.len() {
+            if self.klines.iter().all(|klines| klines[row].is_some()) {
+                for (closes, klines) in closes.iter_mut().zip(&self.klines) {
+                    closes.extend(klines[row].as_ref().map(|kline| kline.c));
+                }
+            }
+        }
+        closes
+    }
+}
+
+/// Places the (sorted) klines of a single series at the given open times.
+fn align_series(
+    klines: &[HistoricalKlineEvent],
+    times: &[i64],
+    interval: &KlineInterval,
+    fill: Fill,
+) -> Vec<Option<HistoricalKlineEvent>> {
+    let mut aligned = Vec::with_capacity(times.len());
+    let mut klines = klines.iter().peekable();
+    let mut previous: Option<&HistoricalKlineEvent> = None;
+    let mut filled = 0;
+
+    for &t in times {
+        while klines.next_if(|kline| kline.t < t).is_some() {}
+        match klines.next_if(|kline| kline.t == t) {
+            Some(kline) => {
+                previous = Some(kline);
+                filled = 0;
+                aligned.push(Some(kline.clone()));
+            }
+            None => {
+                let kline = match (fill, previous) {
+                    (Fill::Forward { limit }, Some(previous))
+                        if limit.is_none_or(|limit| filled < limit) =>
+                    {
+                        filled += 1;
+                        let close_time = interval.next_open_time(t).unwrap_or(t + 1) - 1;
+                        Some(forward_filled(previous, t, close_time))
+                    }
+                    _ => None,
+                };
+                aligned.push(kline);
+            }
+        }
+    }
+    aligned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: i64 = 60_000;
+    // 1 June 2025 00:00 UTC
+    const START: i64 = 1748736000000;
+
+    fn kline(minute: i64, c: f64) -> HistoricalKlineEvent {
+        let t = START + minute * MINUTE;
+        HistoricalKlineEvent {
+            t,
+            o: c,
+            h: c + 1.0,
+            l: c - 1.0,
+            c,
+            v: 1.0,
+            T: t + MINUTE - 1,
+            q: c,
+            n: 1,
+            V: 0.5,
+            Q: c / 2.0,
+            B: "0".to_string(),
+        }
+    }
+
+    fn series(symbol: &str, klines: &[(i64, f64)]) -> KlineSeries {
+        KlineSeries {
+            symbol: symbol.to_string(),
+            interval: KlineInterval::OneMinute,
+            market: Market::Spot,
+            klines: klines
+                .iter()
+                .map(|(minute, c)| kline(*minute, *c))
+                .collect(),
+        }
+    }
+
+    /// ETHUSDT misses minute 2, BTCUSDT misses minutes 4 and 5.
+    fn panel_series() -> Vec<KlineSeries> {
+        vec![
+            series(
+                "ETHUSDT",
+                &[(0, 10.0), (1, 11.0), (3, 13.0), (4, 14.0), (5, 15.0)],
+            ),
+            series("BTCUSDT", &[(0, 20.0), (1, 21.0), (2, 22.0), (3, 23.0)]),
+        ]
+    }
+
+    fn minutes(panel: &KlinePanel) -> Vec<i64> {
+        panel.times.iter().map(|t| (t - START) / MINUTE).collect()
+    }
+
+    #[test]
+    fn inner_join_keeps_common_open_times() {
+        let panel = KlinePanel::align(&panel_series(), Join::Inner, Fill::None).unwrap();
+        assert_eq!(minutes(&panel), vec![0, 1, 3]);
+        assert_eq!(
+            panel.closes("ethusdt"),
+            Some(vec![Some(10.0), Some(11.0), Some(13.0)])
+        );
+        assert_eq!(
+            panel.closes("BTCUSDT"),
+            Some(vec![Some(20.0), Some(21.0), Some(23.0)])
+        );
+        assert_eq!(panel.closes("SOLUSDT"), None);
+    }
+
+    #[test]
+    fn outer_join_leaves_missing_klines_empty() {
+        let panel = KlinePanel::align(&panel_series(), Join::Outer, Fill::None).unwrap();
+        assert_eq!(minutes(&panel), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(
+            panel.closes("ETHUSDT"),
+            Some(vec![
+                Some(10.0),
+                Some(11.0),
+                None,
+                Some(13.0),
+                Some(14.0),
+                Some(15.0)
+            ])
+        );
+        assert_eq!(
+            panel.closes("BTCUSDT"),
+            Some(vec![
+                Some(20.0),
+                Some(21.0),
+                Some(22.0),
+                Some(23.0),
+                None,
+                None
+            ])
+        );
+
+        assert_eq!(
+            panel.matched_closes("ETHUSDT", "BTCUSDT"),
+            Some((vec![10.0, 11.0, 13.0], vec![20.0, 21.0, 23.0]))
+        );
+        assert_eq!(panel.matched_closes("ETHUSDT", "SOLUSDT"), None);
+        assert_eq!(
+            panel.complete_closes(),
+            vec![vec![10.0, 11.0, 13.0], vec![20.0, 21.0, 23.0]]
+        );
+    }
+
+    #[test]
+    fn forward_fill_is_limited() {
+        let fill = Fill::Forward { limit: Some(1) };
+        let panel = KlinePanel::align(&panel_series(), Join::Outer, fill).unwrap();
+
+        let filled = panel.series("ETHUSDT").unwrap()[2].clone().unwrap();
+        assert_eq!(filled.t, START + 2 * MINUTE);
+        assert_eq!(filled.T, START + 3 * MINUTE - 1);
+        assert_eq!(
+            (filled.o, filled.h, filled.l, filled.c),
+            (11.0, 11.0, 11.0, 11.0)
+        );
+        assert_eq!(filled.v, 0.0);
+
+        // Only the first of the two missing klines is filled.
+        assert_eq!(panel.closes("BTCUSDT").unwrap()[4..], [Some(23.0), None]);
+        assert_eq!(
+            panel.complete_closes(),
+            vec![
+                vec![10.0, 11.0, 11.0, 13.0, 14.0],
+                vec![20.0, 21.0, 22.0, 23.0, 23.0]
+            ]
+        );
+
+        let fill = Fill::Forward { limit: None };
+        let panel = KlinePanel::align(&panel_series(), Join::Outer, fill).unwrap();
+        assert_eq!(
+            panel.closes("BTCUSDT").unwrap()[4..],
+            [Some(23.0), Some(23.0)]
+        );
+        assert_eq!(panel.complete_closes()[0].len(), 6);
+    }
+
+    #[test]
+    fn align_requires_series_of_a_single_interval() {
+        assert!(KlinePanel::align(&[], Join::Inner, Fill::None).is_err());
+
+        let mut series = panel_series();
+        series[1].interval = KlineInterval::OneHour;
+        assert!(KlinePanel::align(&series, Join::Inner, Fill::None).is_err());
+    }
+}
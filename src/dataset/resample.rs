@@ -0,0 +1,252 @@
+use crate::binance::stream::KlineInterval;
+use crate::errors::Error;
+use crate::models::HistoricalKlineEvent;
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Whether buckets only partially covered by the source series, i.e. at
+/// the start or end of the series, are included in the resampled series.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartialBuckets {
+    Keep,
+    Drop,
+}
+
+/// Source klines must not straddle the boundaries of the target klines,
+/// which requires the source interval to divide a day, and the target
+/// interval to be a multiple of the source interval.
+fn is_resampleable(source: &KlineInterval, target: &KlineInterval) -> bool {
+    let Some(source_duration) = source.duration_ms() else {
+        return false;
+    };
+    if DAY_MS % source_duration != 0 {
+        return false;
+    }
+    match (target, target.duration_ms()) {
+        (KlineInterval::OneMonth, _) | (KlineInterval::OneWeek, _) => true,
+        (_, Some(target_duration)) => {
+            target_duration > source_duration && target_duration % source_duration == 0
+        }
+        (_, None) => false,
+    }
+}
+
+/// Aggregates the klines of a single bucket.
+fn aggregate(
+    open_time: i64,
+    close_time: i64,
+    klines: &[HistoricalKlineEvent],
+) -> HistoricalKlineEvent {
+    let first = &klines[0];
+    let last = &klines[klines.len() - 1];
+    HistoricalKlineEvent {
+        t: open_time,
+        o: first.o,
+        h: klines.iter().map(|kline| kline.h).fold(f64::MIN, f64::max),
+        l: klines.iter().map(|kline| kline.l).fold(f64::MAX, f64::min),
+        c: last.c,
+        v: klines.iter().map(|kline| kline.v).sum(),
+        T: close_time,
+        q: klines.iter().map(|kline| kline.q).sum(),
+        n: klines.iter().map(|kline| kline.n).sum(),
+        V: klines.iter().map(|kline| kline.V).sum(),
+        Q: klines.iter().map(|kline| kline.Q).sum(),
+        B: "0".to_string(),
+    }
+}
+
+/// Resamples a series of `source` klines, sorted by open time, into
+/// `target` klines aligned to Binance's UTC boundaries.
+pub fn resample(
+    klines: &[HistoricalKlineEvent],
+    source: &KlineInterval,
+    target: &KlineInterval,
+    partial: PartialBuckets,
+) -> Result<Vec<HistoricalKlineEvent>, Error> {
+    if !is_resampleable(source, target) {
+        return Err(format!("Unable to resample {source} klines to {target} klines").into());
+    }
+
+    let mut resampled = Vec::new();
+    let mut start = 0;
+    while start < klines.len() {
+        let open_time = target
+            .open_time_containing(klines[start].t)
+            .ok_or_else(|| Error::Parse(format!("Invalid open time {}", klines[start].t)))?;
+        let next_open_time = target
+            .next_open_time(open_time)
+            .ok_or_else(|| Error::Parse(format!("Invalid open time {open_time}")))?;
+
+        let end = start
+            + klines[start..]
+                .iter()
+                .take_while(|kline| kline.t < next_open_time)
+                .count();
+        let bucket = &klines[start..end];
+        start = end;
+
+        // Buckets are complete if covered from their open to their close,
+        // which is only not the case at the start or end of a series.
+        let is_complete = bucket[0].t == open_time
+            && source.next_open_time(bucket[bucket.len() - 1].t) == Some(next_open_time);
+        if !is_complete && partial == PartialBuckets::Drop {
+            continue;
+        }
+        resampled.push(aggregate(open_time, next_open_time - 1, bucket));
+    }
+    Ok(resampled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    const MINUTE_MS: i64 = 60 * 1000;
+
+    fn timestamp(year: i32, month: u32, day: u32) -> i64 {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc()
+            .timestamp_millis()
+    }
+
+    /// Consecutive klines of `duration`, the n-th kline closing at n + 1.
+    fn series(start: i64, duration: i64, count: i64) -> Vec<HistoricalKlineEvent> {
+        (0..count)
+            .map(|n| {
+                let t = start + n * duration;
+                let price = (n + 1) as f64;
+                HistoricalKlineEvent {
+                    t,
+                    o: price - 0.5,
+                    h: price + 1.0,
+                    l: price - 1.0,
+                    c: price,
+                    v: 1.0,
+                    T: t + duration - 1,
+                    q: price,
+                    n: 2,
+                    V: 0.5,
+                    Q: price / 2.0,
+                    B: "0".to_string(),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn minutes_to_hours() {
+        let start = timestamp(2025, 6, 1);
+        let klines = series(start, MINUTE_MS, 120);
+        let hours = resample(
+            &klines,
+            &KlineInterval::OneMinute,
+            &KlineInterval::OneHour,
+            PartialBuckets::Keep,
+        )
+        .unwrap();
+
+        assert_eq!(hours.len(), 2);
+        let first = &hours[0];
+        assert_eq!(first.t, start);
+        assert_eq!(first.T, start + 60 * MINUTE_MS - 1);
+        assert_eq!((first.o, first.h, first.l, first.c), (0.5, 61.0, 0.0, 60.0));
+        assert_eq!(first.v, 60.0);
+        assert_eq!(first.n, 120);
+        assert_eq!(hours[1].t, start + 60 * MINUTE_MS);
+        assert_eq!(hours[1].c, 120.0);
+    }
+
+    #[test]
+    fn days_to_weeks_open_on_monday() {
+        // Thursday 29 May up to and including Sunday 15 June 2025.
+        let klines = series(timestamp(2025, 5, 29), DAY_MS, 18);
+        let weeks = resample(
+            &klines,
+            &KlineInterval::OneDay,
+            &KlineInterval::OneWeek,
+            PartialBuckets::Keep,
+        )
+        .unwrap();
+
+        let open_times: Vec<i64> = weeks.iter().map(|kline| kline.t).collect();
+        assert_eq!(
+            open_times,
+            vec![
+                timestamp(2025, 5, 26),
+                timestamp(2025, 6, 2),
+                timestamp(2025, 6, 9)
+            ]
+        );
+        assert_eq!(weeks[1].T, timestamp(2025, 6, 9) - 1);
+        // The first week only contains Thursday up to Sunday.
+        assert_eq!(weeks[0].v, 4.0);
+        assert_eq!(weeks[1].v, 7.0);
+    }
+
+    #[test]
+    fn days_to_months() {
+        // 1 May up to and including 30 June 2025.
+        let klines = series(timestamp(2025, 5, 1), DAY_MS, 61);
+        let months = resample(
+            &klines,
+            &KlineInterval::OneDay,
+            &KlineInterval::OneMonth,
+            PartialBuckets::Drop,
+        )
+        .unwrap();
+
+        assert_eq!(months.len(), 2);
+        assert_eq!(months[0].t, timestamp(2025, 5, 1));
+        assert_eq!(months[0].T, timestamp(2025, 6, 1) - 1);
+        assert_eq!(months[0].v, 31.0);
+        assert_eq!(months[0].c, 31.0);
+        assert_eq!(months[1].t, timestamp(2025, 6, 1));
+        assert_eq!(months[1].T, timestamp(2025, 7, 1) - 1);
+        assert_eq!(months[1].v, 30.0);
+    }
+
+    #[test]
+    fn partial_buckets_are_dropped() {
+        // 00:30 up to and including 02:29.
+        let start = timestamp(2025, 6, 1) + 30 * MINUTE_MS;
+        let klines = series(start, MINUTE_MS, 120);
+
+        let kept = resample(
+            &klines,
+            &KlineInterval::OneMinute,
+            &KlineInterval::OneHour,
+            PartialBuckets::Keep,
+        )
+        .unwrap();
+        assert_eq!(kept.len(), 3);
+
+        let dropped = resample(
+            &klines,
+            &KlineInterval::OneMinute,
+            &KlineInterval::OneHour,
+            PartialBuckets::Drop,
+        )
+        .unwrap();
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0].t, timestamp(2025, 6, 1) + 60 * MINUTE_MS);
+        assert_eq!(dropped[0].v, 60.0);
+    }
+
+    #[test]
+    fn incompatible_intervals() {
+        let klines = series(timestamp(2025, 6, 1), DAY_MS, 2);
+        assert!(
+            resample(
+                &klines,
+                &KlineInterval::OneDay,
+                &KlineInterval::OneHour,
+                PartialBuckets::Keep
+            )
+            .is_err()
+        );
+    }
+}
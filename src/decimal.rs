@@ -0,0 +1,176 @@
+use std::str::FromStr;
+
+use crate::errors::Error;
+
+// Number of decimals represented exactly.
+const DECIMALS: usize = 18;
+const SCALE: i128 = 10i128.pow(DECIMALS as u32);
+
+/// Exact decimal number with up to 18 decimals, e.g. a price or quantity
+/// received as a string. Unlike `f64`, equal prices are always equal,
+/// which allows them to be used as keys of an order book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Decimal(i128);
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal(0);
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Number of decimals, excluding trailing zeros, e.g. 8 for `0.00000001`.
+    pub fn decimals(self) -> u32 {
+        let mut fraction = self.0 % SCALE;
+        if fraction == 0 {
+            return 0;
+        }
+        let mut decimals = DECIMALS as u32;
+        while fraction % 10 == 0 {
+            fraction /= 10;
+            decimals -= 1;
+        }
+        decimals
+    }
+
+    pub fn to_f64(self) -> f64 {
+        (self.0 / SCALE) as f64 + (self.0 % SCALE) as f64 / SCALE as f64
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || Error::Parse(format!("Unable to parse {s:?} to decimal"));
+
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        // Trailing zeros beyond the supported decimals do not lose precision.
+        let fraction = fraction.trim_end_matches('0');
+        if (integer.is_empty() && fraction.is_empty())
+            || fraction.len() > DECIMALS
+            || !integer
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(error());
+        }
+
+        let integer = match integer {
+            "" => 0,
+            integer => integer.parse::<i128>().map_err(|_| error())?,
+        };
+        let fraction = match fraction {
+            "" => 0,
+            fraction => {
+                fraction.parse::<i128>().map_err(|_| error())?
+                    * 10i128.pow((DECIMALS - fraction.len()) as u32)
+            }
+        };
+        let value = integer
+            .checked_mul(SCALE)
+            .and_then(|value| value.checked_add(fraction))
+            .ok_or_else(error)?;
+        Ok(Self(if negative { -value } else { value }))
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let integer = (self.0 / SCALE).unsigned_abs();
+        let fraction = (self.0 % SCALE).unsigned_abs();
+        if fraction == 0 {
+            return write!(f, "{sign}{integer}");
+        }
+        let fraction = format!("{fraction:0DECIMALS$}");
+        write!(f, "{sign}{integer}.{}", fraction.trim_end_matches('0'))
+    }
+}
+
+impl std::ops::Add for Decimal {
+    type Output = Decimal;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl std::ops::Sub for Decimal {
+    type Output = Decimal;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self(self.0 - rhs.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        for s in [
+            "0",
+            "1",
+            "-1",
+            "0.00000001",
+            "104591.88",
+            "-0.5",
+            "0.000000000000000001",
+            "170141183460469231731.687303715884105727",
+        ] {
+            assert_eq!(decimal(s).to_string(), s);
+        }
+    }
+
+    #[test]
+    fn trailing_and_leading_zeros() {
+        assert_eq!(decimal("0.00100000").to_string(), "0.001");
+        assert_eq!(decimal("100.000").to_string(), "100");
+        assert_eq!(decimal("007.50").to_string(), "7.5");
+        assert_eq!(decimal(".5"), decimal("0.5"));
+        assert_eq!(decimal("5."), decimal("5"));
+        assert_eq!(decimal("0.10000000000000000000"), decimal("0.1"));
+        assert_eq!(decimal("-0.0"), Decimal::ZERO);
+        assert!(decimal("0.00000000").is_zero());
+    }
+
+    #[test]
+    fn invalid() {
+        for s in [
+            "",
+            ".",
+            "-",
+            "1e-8",
+            "1.2.3",
+            "+1",
+            "0.0000000000000000001",
+            "170141183460469231732",
+            " 1",
+        ] {
+            assert!(s.parse::<Decimal>().is_err(), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn arithmetic_and_conversion() {
+        assert_eq!(decimal("0.1") + decimal("0.2"), decimal("0.3"));
+        assert_eq!(decimal("0.0026") - decimal("0.0024"), decimal("0.0002"));
+        assert!(decimal("0.0024") < decimal("0.0025"));
+        assert!(decimal("-1") < Decimal::ZERO);
+        assert_eq!(decimal("104591.88").to_f64(), 104591.88);
+        assert_eq!(decimal("-2.5").to_f64(), -2.5);
+        assert_eq!(decimal("0.00000001").decimals(), 8);
+        assert_eq!(decimal("100").decimals(), 0);
+    }
+}
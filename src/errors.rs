@@ -6,6 +6,8 @@ pub enum Error {
     Serde(serde_json::Error),
     Zip(zip::result::ZipError),
     Parse(String),
+    Checksum(String),
+    ArchiveNotAvailable(String),
     Other(String),
 }
 
@@ -18,6 +20,8 @@ impl std::fmt::Display for Error {
             Self::Serde(e) => write!(f, "{e}"),
             Self::Zip(e) => write!(f, "{e}"),
             Self::Parse(e) => write!(f, "{e}"),
+            Self::Checksum(e) => write!(f, "{e}"),
+            Self::ArchiveNotAvailable(e) => write!(f, "Archive not available: {e}"),
             Self::Other(e) => write!(f, "{e}"),
         }
     }
@@ -72,3 +76,9 @@ impl From<std::num::ParseFloatError> for Error {
         Error::Parse(value.to_string())
     }
 }
+
+impl From<tokio::task::JoinError> for Error {
+    fn from(value: tokio::task::JoinError) -> Self {
+        Error::Other(value.to_string())
+    }
+}
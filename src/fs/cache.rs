@@ -0,0 +1,284 @@
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::errors::Error;
+use crate::fs::read::read_delimited_from_zip_file;
+use crate::models::HistoricalKlineEvent;
+
+// Parsed klines are cached in a columnar binary file next to the archive
+// they were parsed from, consisting of a fixed size header followed by one
+// little endian column per field:
+//
+// magic (4) | version (u32) | source size (u64) | source modified (u64, ns) | rows (u64)
+// t (i64) | o | h | l | c | v (f64) | T (i64) | q (f64) | n (u64) | V | Q (f64)
+const MAGIC: &[u8; 4] = b"BNYK";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 4 + 4 + 8 + 8 + 8;
+const COLUMNS: usize = 11;
+const CACHE_EXTENSION: &str = "cache";
+
+/// Location of the cache of an archive.
+pub fn get_cache_path<P: AsRef<Path>>(archive: P) -> PathBuf {
+    let archive = archive.as_ref();
+    PathBuf::from(format!("{}.{CACHE_EXTENSION}", archive.to_string_lossy()))
+}
+
+/// Size and modification time (in nanoseconds) of the source archive,
+/// used to invalidate the cache once the archive changes.
+fn source_fingerprint(source: &Path) -> Result<(u64, u64), Error> {
+    let metadata = std::fs::metadata(source)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or_default();
+    Ok((metadata.len(), modified))
+}
+
+fn write_column<F>(bytes: &mut Vec<u8>, klines: &[HistoricalKlineEvent], value: F)
+where
+    F: Fn(&HistoricalKlineEvent) -> [u8; 8],
+{
+    for kline in klines {
+        bytes.extend_from_slice(&value(kline));
+    }
+}
+
+/// Writes the klines parsed from `source` to the cache at `path`.
+pub fn write_kline_cache<P, S>(
+    path: P,
+    source: S,
+    klines: &[HistoricalKlineEvent],
+) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+    S: AsRef<Path>,
+{
+    let (size, modified) = source_fingerprint(source.as_ref())?;
+    let rows = klines.len();
+
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + COLUMNS * 8 * rows);
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&size.to_le_bytes());
+    bytes.extend_from_slice(&modified.to_le_bytes());
+    bytes.extend_from_slice(&(rows as u64).to_le_bytes());
+
+    // Written in the order documented above.
+    write_column(&mut bytes, klines, |kline| kline.t.to_le_bytes());
+    write_column(&mut bytes, klines, |kline| kline.o.to_le_bytes());
+    write_column(&mut bytes, klines, |kline| kline.h.to_le_bytes());
+    write_column(&mut bytes, klines, |kline| kline.l.to_le_bytes());
+    write_column(&mut bytes, klines, |kline| kline.c.to_le_bytes());
+    write_column(&mut bytes, klines, |kline| kline.v.to_le_bytes());
+    write_column(&mut bytes, klines, |kline| kline.T.to_le_bytes());
+    write_column(&mut bytes, klines, |kline| kline.q.to_le_bytes());
+    write_column(&mut bytes, klines, |kline| kline.n.to_le_bytes());
+    write_column(&mut bytes, klines, |kline| kline.V.to_le_bytes());
+    write_column(&mut bytes, klines, |kline| kline.Q.to_le_bytes());
+
+    // Written to a temporary file first, so that an interrupted
+    // write never leaves a truncated cache behind.
+    let path = path.as_ref();
+    let temporary = PathBuf::from(format!("{}.tmp", path.to_string_lossy()));
+    std::fs::write(&temporary, bytes)?;
+    std::fs::rename(temporary, path)?;
+    Ok(())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut buffer = [0; 8];
+    buffer.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(buffer)
+}
+
+/// Reads the klines cached at `path`. Returns `None` if the cache does
+/// not exist, is of a different version, or `source` changed since the
+/// cache was written.
+pub fn read_kline_cache<P, S>(
+    path: P,
+    source: S,
+) -> Result<Option<Vec<HistoricalKlineEvent>>, Error>
+where
+    P: AsRef<Path>,
+    S: AsRef<Path>,
+{
+    let path = path.as_ref();
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let bytes = std::fs::read(path)?;
+    if bytes.len() < HEADER_SIZE
+        || &bytes[..4] != MAGIC
+        || bytes[4..8] != VERSION.to_le_bytes()
+        || (read_u64(&bytes, 8), read_u64(&bytes, 16)) != source_fingerprint(source.as_ref())?
+    {
+        return Ok(None);
+    }
+
+    // The row count is untrusted, hence overflowing sizes are a cache miss.
+    let Ok(rows) = usize::try_from(read_u64(&bytes, 24)) else {
+        return Ok(None);
+    };
+    let size = rows
+        .checked_mul(COLUMNS * 8)
+        .and_then(|size| size.checked_add(HEADER_SIZE));
+    if size != Some(bytes.len()) {
+        return Ok(None);
+    }
+
+    let column = |index: usize| {
+        let start = HEADER_SIZE + index * 8 * rows;
+        bytes[start..start + 8 * rows].chunks_exact(8).map(|chunk| {
+            let mut buffer = [0; 8];
+            buffer.copy_from_slice(chunk);
+            buffer
+        })
+    };
+    let i64_column = |index| column(index).map(i64::from_le_bytes);
+    let f64_column = |index| column(index).map(f64::from_le_bytes);
+
+    let mut t = i64_column(0);
+    let mut o = f64_column(1);
+    let mut h = f64_column(2);
+    let mut l = f64_column(3);
+    let mut c = f64_column(4);
+    let mut v = f64_column(5);
+    let mut close_time = i64_column(6);
+    let mut q = f64_column(7);
+    let mut n = column(8).map(u64::from_le_bytes);
+    let mut taker_base = f64_column(9);
+    let mut taker_quote = f64_column(10);
+
+    let mut klines = Vec::with_capacity(rows);
+    for _ in 0..rows {
+        // Every column contains exactly `rows` values, as verified above.
+        klines.push(HistoricalKlineEvent {
+            t: t.next().unwrap_or_default(),
+            o: o.next().unwrap_or_default(),
+            h: h.next().unwrap_or_default(),
+            l: l.next().unwrap_or_default(),
+            c: c.next().unwrap_or_default(),
+            v: v.next().unwrap_or_default(),
+            T: close_time.next().unwrap_or_default(),
+            q: q.next().unwrap_or_default(),
+            n: n.next().unwrap_or_default(),
+            V: taker_base.next().unwrap_or_default(),
+            Q: taker_quote.next().unwrap_or_default(),
+            B: "0".to_string(),
+        });
+    }
+    Ok(Some(klines))
+}
+
+/// Reads the klines of an archive from its cache, parsing the archive
+/// (and caching the result) only if the cache is missing or outdated.
+/// This blocks while reading, similar to `read_delimited_from_zip_file`.
+pub fn read_klines_from_zip_file_cached<P: AsRef<Path>>(
+    path: P,
+) -> Result<Vec<HistoricalKlineEvent>, Error> {
+    let path = path.as_ref();
+    let cache_path = get_cache_path(path);
+    if let Some(klines) = read_kline_cache(&cache_path, path)? {
+        return Ok(klines);
+    }
+
+    let klines: Vec<HistoricalKlineEvent> = read_delimited_from_zip_file(path)?;
+    // The cache is an optimisation only, failing to write it (e.g. on a
+    // read only volume) should not prevent the klines from being loaded.
+    let _ = write_kline_cache(&cache_path, path, &klines);
+    Ok(klines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance::historical::tests::temp_dir;
+    use std::time::Duration;
+
+    fn kline(t: i64, c: f64) -> HistoricalKlineEvent {
+        HistoricalKlineEvent {
+            t,
+            o: c - 1.0,
+            h: c + 1.0,
+            l: c - 2.0,
+            c,
+            v: 3.5,
+            T: t + 59_999,
+            q: c * 3.5,
+            n: 42,
+            V: 1.25,
+            Q: c * 1.25,
+            B: "0".to_string(),
+        }
+    }
+
+    /// Source archive, its cache path and the klines cached for it.
+    fn cached(name: &str) -> (PathBuf, PathBuf, PathBuf, Vec<HistoricalKlineEvent>) {
+        let dir = temp_dir(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("BTCUSDT-1m-2025-06-01.zip");
+        std::fs::write(&source, "archive").unwrap();
+        let path = get_cache_path(&source);
+        let klines = vec![kline(1748736000000, 10.0), kline(1748736060000, 11.0)];
+        write_kline_cache(&path, &source, &klines).unwrap();
+        (dir, source, path, klines)
+    }
+
+    fn overwrite(path: &Path, offset: usize, replacement: &[u8]) {
+        let mut bytes = std::fs::read(path).unwrap();
+        bytes[offset..offset + replacement.len()].copy_from_slice(replacement);
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn cache_round_trip() {
+        let (dir, source, path, klines) = cached("cache-round-trip");
+        assert_eq!(read_kline_cache(&path, &source).unwrap(), Some(klines));
+        assert_eq!(
+            read_kline_cache(dir.join("missing.cache"), &source).unwrap(),
+            None
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn changed_source_invalidates_cache() {
+        let (_, source, path, _) = cached("cache-source");
+        std::fs::write(&source, "changed archive").unwrap();
+        assert_eq!(read_kline_cache(&path, &source).unwrap(), None);
+
+        // Same size, but modified at a different time.
+        let (dir, source, path, _) = cached("cache-source");
+        let modified = std::fs::metadata(&source).unwrap().modified().unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&source)
+            .unwrap()
+            .set_modified(modified - Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(read_kline_cache(&path, &source).unwrap(), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_header_is_a_cache_miss() {
+        let (dir, source, path, klines) = cached("cache-header");
+        overwrite(&path, 0, b"ZIPK");
+        assert_eq!(read_kline_cache(&path, &source).unwrap(), None);
+
+        write_kline_cache(&path, &source, &klines).unwrap();
+        overwrite(&path, 4, &(VERSION + 1).to_le_bytes());
+        assert_eq!(read_kline_cache(&path, &source).unwrap(), None);
+
+        // Row counts overflowing the expected size are not trusted.
+        write_kline_cache(&path, &source, &klines).unwrap();
+        overwrite(&path, 24, &u64::MAX.to_le_bytes());
+        assert_eq!(read_kline_cache(&path, &source).unwrap(), None);
+
+        write_kline_cache(&path, &source, &klines).unwrap();
+        std::fs::write(&path, &std::fs::read(&path).unwrap()[..HEADER_SIZE + 8]).unwrap();
+        assert_eq!(read_kline_cache(&path, &source).unwrap(), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
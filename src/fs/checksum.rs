@@ -0,0 +1,39 @@
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+use crate::errors::Error;
+
+/// Computes the hex encoded SHA-256 digest of `bytes`.
+pub fn sha256_digest<B: AsRef<[u8]>>(bytes: &B) -> String {
+    format!("{:x}", Sha256::digest(bytes.as_ref()))
+}
+
+/// Computes the hex encoded SHA-256 digest of the file at `path`,
+/// without reading the whole file into memory.
+pub fn sha256_file_digest<P: AsRef<Path>>(path: P) -> Result<String, Error> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Extracts the digest from the content of a `.CHECKSUM` file,
+/// which follows the `sha256sum` format i.e. `<digest>  <file name>`.
+pub fn parse_checksum(content: &str) -> Option<String> {
+    content
+        .split_whitespace()
+        .next()
+        .filter(|digest| digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(|digest| digest.to_ascii_lowercase())
+}
+
+/// Ensures the digest of `bytes` matches the `expected` digest.
+pub fn verify_digest<B: AsRef<[u8]>>(bytes: &B, expected: &str, name: &str) -> Result<(), Error> {
+    let actual = sha256_digest(bytes);
+    if actual != expected {
+        return Err(Error::Checksum(format!(
+            "Checksum mismatch for {name}: expected {expected}, found {actual}"
+        )));
+    }
+    Ok(())
+}
@@ -1,3 +1,5 @@
+pub mod cache;
+pub mod checksum;
 pub mod parse;
 pub mod read;
 pub mod write;
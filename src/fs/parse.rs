@@ -1,3 +1,4 @@
+use crate::decimal::Decimal;
 use crate::errors::Error as ProjectError;
 use chrono::offset::LocalResult;
 use chrono::{DateTime, TimeZone, Utc};
@@ -7,24 +8,29 @@ use serde::{Deserialize, Deserializer};
 // 1 January 2025
 const TIMESTAMP_CHANGE_2025_MS: i64 = 1735689600000;
 
-fn micro_seconds_to_datetime(t: &i64) -> LocalResult<DateTime<Utc>> {
-    milli_seconds_to_datetime(&(t / 1000))
-}
+// 1 January 2025, in microseconds. Millisecond timestamps only reach this
+// value tens of thousands of years from now, hence any timestamp at least
+// this large is in microseconds.
+const TIMESTAMP_CHANGE_2025_US: i64 = TIMESTAMP_CHANGE_2025_MS * 1000;
 
 fn milli_seconds_to_datetime(t: &i64) -> LocalResult<DateTime<Utc>> {
-    let secs = t / 1000;
-    let nsecs = ((t % 1000) * 1_000_000) as u32;
+    let secs = t.div_euclid(1000);
+    let nsecs = (t.rem_euclid(1000) * 1_000_000) as u32;
     Utc.timestamp_opt(secs, nsecs)
 }
 
 // Binance changed the unit of their server timestamps
 // in 2025 from milli- to micro- seconds.
-pub fn binance_timestamp_to_datetime(t: &i64) -> Option<DateTime<Utc>> {
-    let timestamp = if t > &TIMESTAMP_CHANGE_2025_MS {
-        micro_seconds_to_datetime(t)
+pub fn binance_timestamp_to_milliseconds(t: i64) -> i64 {
+    if t >= TIMESTAMP_CHANGE_2025_US {
+        t / 1000
     } else {
-        milli_seconds_to_datetime(t)
-    };
+        t
+    }
+}
+
+pub fn binance_timestamp_to_datetime(t: &i64) -> Option<DateTime<Utc>> {
+    let timestamp = milli_seconds_to_datetime(&binance_timestamp_to_milliseconds(*t));
 
     if let chrono::LocalResult::Single(datetime) = timestamp {
         return Some(datetime);
@@ -41,3 +47,62 @@ where
         .parse::<f64>()
         .map_err(|_| D::Error::custom(ProjectError::Parse(String::from("Unable to parse to f64"))))
 }
+
+pub fn string_to_decimal<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: &str = Deserialize::deserialize(deserializer)?;
+    s.trim_matches('"')
+        .parse::<Decimal>()
+        .map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn milliseconds_are_unchanged() {
+        // 1 June 2024 00:00:00 UTC
+        assert_eq!(
+            binance_timestamp_to_milliseconds(1717200000000),
+            1717200000000
+        );
+        assert_eq!(
+            binance_timestamp_to_milliseconds(TIMESTAMP_CHANGE_2025_MS),
+            TIMESTAMP_CHANGE_2025_MS
+        );
+    }
+
+    #[test]
+    fn microseconds_are_normalised() {
+        // 1 June 2025 00:00:00 UTC
+        assert_eq!(
+            binance_timestamp_to_milliseconds(1748736000000000),
+            1748736000000
+        );
+        // Close times end in 999 microseconds, which are truncated.
+        assert_eq!(
+            binance_timestamp_to_milliseconds(1748739599999999),
+            1748739599999
+        );
+        assert_eq!(
+            binance_timestamp_to_milliseconds(TIMESTAMP_CHANGE_2025_US),
+            TIMESTAMP_CHANGE_2025_MS
+        );
+    }
+
+    #[test]
+    fn datetime_from_either_unit() {
+        let expected = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            binance_timestamp_to_datetime(&1748736000000),
+            Some(expected)
+        );
+        assert_eq!(
+            binance_timestamp_to_datetime(&1748736000000000),
+            Some(expected)
+        );
+    }
+}
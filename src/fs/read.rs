@@ -1,28 +1,152 @@
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
-use std::{fs, io::Read};
 use zip::ZipArchive;
+use zip::read::ZipFile;
 
 use crate::errors;
+use crate::models::FromDelimitedString;
 
-/// Reads the content of a CSV file inside a ZIP archive.
-/// The function expects that the ZIP archive contains a single CSV file whose
-/// filename matches the name of the ZIP file (excluding the `.zip` extension)
-/// with a `.csv` extension.
-pub async fn read_csv_from_zip_file<P: AsRef<Path>>(path: P) -> Result<String, errors::Error> {
-    let zip_path = path.as_ref();
-    let file = std::fs::File::open(zip_path)?;
-    let reader = std::io::BufReader::new(file);
-    let mut archive = ZipArchive::new(reader)?;
-
-    let mut content = String::new();
-    if let Some(file_stem_osstr) = zip_path.file_stem() {
-        if let Some(file_stem) = file_stem_osstr.to_str() {
-            let filename = format!("{file_stem}.csv");
-            let mut zip_file = archive.by_name(&filename)?;
-            zip_file.read_to_string(&mut content)?;
+/// Parses every row of the CSV file inside a ZIP archive, see
+/// `ZipCsvReader`. A header row, if present, is skipped.
+///
+/// This blocks while reading, use `read_files_in_parallel`
+/// when reading from within the async runtime.
+pub fn read_delimited_from_zip_file<P, T>(path: P) -> Result<Vec<T>, errors::Error>
+where
+    P: AsRef<Path>,
+    T: for<'a> FromDelimitedString<&'a str>,
+{
+    let mut reader = ZipCsvReader::open(path)?;
+    reader.records()?.collect()
+}
+
+/// Reads files using the blocking function `read`, distributing the files
+/// over the blocking thread pool so that they are decompressed and parsed
+/// in parallel without blocking the async runtime. The records are returned
+/// in the order of `paths`.
+pub async fn read_files_in_parallel<T, F>(
+    paths: Vec<PathBuf>,
+    read: F,
+) -> Result<Vec<T>, errors::Error>
+where
+    T: Send + 'static,
+    F: Fn(&Path) -> Result<Vec<T>, errors::Error> + Send + Sync + 'static,
+{
+    let parallelism = std::thread::available_parallelism()
+        .map(|parallelism| parallelism.get())
+        .unwrap_or(1);
+    let read = std::sync::Arc::new(read);
+
+    let mut results: Vec<Option<Vec<T>>> = Vec::new();
+    results.resize_with(paths.len(), || None);
+
+    let mut reads = tokio::task::JoinSet::new();
+    for (index, path) in paths.into_iter().enumerate() {
+        // Wait for a file to be read before exceeding the available parallelism.
+        if reads.len() >= parallelism
+            && let Some(result) = reads.join_next().await
+        {
+            let (index, records) = result?;
+            results[index] = Some(records?);
+        }
+
+        let read = read.clone();
+        reads.spawn_blocking(move || (index, read(&path)));
+    }
+
+    while let Some(result) = reads.join_next().await {
+        let (index, records) = result?;
+        results[index] = Some(records?);
+    }
+
+    Ok(results.into_iter().flatten().flatten().collect())
+}
+
+/// Reads the CSV file inside a ZIP archive row by row, rather than reading
+/// the whole file into memory. The archive is expected to contain a single
+/// CSV file, named after the archive (excluding the `.zip` extension).
+pub struct ZipCsvReader {
+    archive: ZipArchive<BufReader<fs::File>>,
+    file_name: String,
+}
+
+impl ZipCsvReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, errors::Error> {
+        let zip_path = path.as_ref();
+        let file_stem = zip_path
+            .file_stem()
+            .and_then(|file_stem| file_stem.to_str())
+            .ok_or_else(|| format!("Invalid archive name {}", zip_path.to_string_lossy()))?;
+        let file = fs::File::open(zip_path)?;
+        Ok(Self {
+            archive: ZipArchive::new(BufReader::new(file))?,
+            file_name: format!("{file_stem}.csv"),
+        })
+    }
+
+    /// Parses the rows one at a time, decompressing the file as it is read.
+    pub fn records<T>(&mut self) -> Result<DelimitedRecords<'_, T>, errors::Error>
+    where
+        T: for<'a> FromDelimitedString<&'a str>,
+    {
+        let zip_file = self.archive.by_name(&self.file_name)?;
+        Ok(DelimitedRecords {
+            reader: BufReader::new(zip_file),
+            file_name: &self.file_name,
+            line: String::new(),
+            line_number: 0,
+            delimiter: ',',
+            marker: PhantomData,
+        })
+    }
+}
+
+/// Iterator over the parsed rows of a `ZipCsvReader`. A single line
+/// buffer is reused for all rows, and errors include the file name
+/// and line number of the offending row.
+pub struct DelimitedRecords<'a, T> {
+    reader: BufReader<ZipFile<'a, BufReader<fs::File>>>,
+    file_name: &'a str,
+    line: String,
+    line_number: usize,
+    delimiter: char,
+    marker: PhantomData<T>,
+}
+
+impl<T> DelimitedRecords<'_, T> {
+    fn error(&self, e: errors::Error) -> errors::Error {
+        errors::Error::Parse(format!("{}:{}: {}", self.file_name, self.line_number, e))
+    }
+}
+
+impl<T> Iterator for DelimitedRecords<'_, T>
+where
+    T: for<'a> FromDelimitedString<&'a str>,
+{
+    type Item = Result<T, errors::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => self.line_number += 1,
+                Err(e) => return Some(Err(self.error(e.into()))),
+            }
+
+            let line = self.line.trim_end_matches(['\r', '\n']);
+            if line.is_empty() {
+                continue;
+            }
+            // Some archives start with a header row, which is skipped.
+            if self.line_number == 1 && !line.starts_with(|c: char| c.is_ascii_digit()) {
+                continue;
+            }
+            return Some(T::from_delimited_string(line, self.delimiter).map_err(|e| self.error(e)));
         }
     }
-    Ok(content)
 }
 
 /// Creates a collection of all files within a
@@ -38,3 +162,77 @@ pub fn identify_files<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<PathBuf>>
     }
     Ok(files)
 }
+
+/// Creates a collection of all files within a specified
+/// directory and all of its sub directories.
+pub fn identify_files_recursively<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut directories = vec![path.as_ref().to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                directories.push(path);
+            } else if path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance::historical::tests::{temp_dir, zip_archive};
+    use crate::models::HistoricalKlineEvent;
+
+    const FILE: &str = "BTCUSDT-1m-2025-06-01";
+    const FIRST: &str = "1748736000000,10.0,11.0,9.0,10.5,2.0,1748736059999,21.0,4,1.0,10.5,0";
+    const SECOND: &str = "1748736060000,10.5,12.0,10.0,11.5,3.0,1748736119999,34.5,6,1.5,17.25,0";
+
+    /// Writes an archive containing `content` as its CSV file.
+    fn write_archive(name: &str, content: &str) -> (PathBuf, PathBuf) {
+        let dir = temp_dir(name);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{FILE}.zip"));
+        fs::write(&path, zip_archive(&format!("{FILE}.csv"), content)).unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn header_and_blank_lines_are_skipped() {
+        let content = format!(
+            "open_time,open,high,low,close,volume,close_time,quote_volume,count,\
+             taker_buy_volume,taker_buy_quote_volume,ignore\r\n{FIRST}\r\n\r\n{SECOND}\n\n"
+        );
+        let (dir, path) = write_archive("read-header", &content);
+
+        let klines: Vec<HistoricalKlineEvent> = read_delimited_from_zip_file(&path).unwrap();
+        let open_times: Vec<i64> = klines.iter().map(|kline| kline.t).collect();
+        assert_eq!(open_times, vec![1748736000000, 1748736060000]);
+        assert_eq!(klines[0].B, "0");
+        assert_eq!(klines[1].Q, 17.25);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn errors_include_file_and_line() {
+        let content = format!("{FIRST}\n\n{SECOND}\n1748736120000,not a price\n");
+        let (dir, path) = write_archive("read-errors", &content);
+
+        let mut reader = ZipCsvReader::open(&path).unwrap();
+        let records: Vec<Result<HistoricalKlineEvent, errors::Error>> =
+            reader.records().unwrap().collect();
+        assert_eq!(records.len(), 3);
+        assert!(records[..2].iter().all(Result::is_ok));
+        match &records[2] {
+            Err(errors::Error::Parse(message)) => {
+                assert!(message.starts_with(&format!("{FILE}.csv:4: ")), "{message}")
+            }
+            other => panic!("expected a parse error, found {other:?}"),
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -24,6 +24,8 @@ where
     let mut file = tokio::fs::File::create(path).await?;
 
     file.write_all(&bytes.as_ref()).await?;
+    // Otherwise the write may still be in flight once this returns.
+    file.flush().await?;
 
     Ok(())
 }
@@ -1,17 +1,20 @@
 mod binance;
+mod dataset;
+mod decimal;
 mod errors;
 mod fs;
 mod math;
 mod models;
 mod strategy;
 
+use crate::binance::historical::DownloadOptions;
+use crate::binance::stream::KlineInterval;
+use crate::binance::stream::{MarketStream, stream_to_channel};
+use crate::dataset::kline::KlineDataset;
 use crate::errors::Error;
-use crate::fs::read::read_csv_from_zip_file;
-use crate::models::{FromDelimitedString, HistoricalKlineEvent};
 use crate::strategy::decision::{PositionAction, PositionDirection, PositionParameters};
 use crate::strategy::simple::{SimpleAverage, SimpleStrategy};
-use crate::{binance::stream::stream_to_channel, fs::read::identify_files};
-use chrono::{Months, NaiveDate};
+use chrono::NaiveDate;
 use strategy::decision::{HandleStreamEvent, TradingStrategy};
 
 // Identify two (or more) assets that are historically correlated or cointegrated.
@@ -46,7 +49,7 @@ fn temp_stream() -> Result<(), Error> {
     let (tx1, rx1) = std::sync::mpsc::channel::<models::KlineEvent>();
 
     let frequency = binance::stream::KlineInterval::OneSecond;
-    let btc_handle = stream_to_channel("btcusdt", &frequency, tx1);
+    let btc_handle = stream_to_channel(MarketStream::kline("btcusdt", &frequency), tx1);
 
     let handle1 = std::thread::spawn(move || -> Result<(), Error> {
         let mut position = PositionParameters::default();
@@ -74,20 +77,6 @@ fn temp_stream() -> Result<(), Error> {
     Ok(())
 }
 
-fn generate_date_range(start: NaiveDate, end: NaiveDate) -> impl Iterator<Item = NaiveDate> {
-    let mut current = start;
-    std::iter::from_fn(move || {
-        if current <= end {
-            if let Some(next) = current.checked_add_months(Months::new(1)) {
-                let previous = current;
-                current = next;
-                return Some(previous);
-            }
-        }
-        None
-    })
-}
-
 #[derive(Debug)]
 struct TrackPositionMovement {
     price: f64,
@@ -131,42 +120,46 @@ async fn main() -> Result<(), Error> {
 
     let start = NaiveDate::from_ymd_opt(2019, 7, 1).unwrap();
     // let start = NaiveDate::from_ymd_opt(2024, 8, 1).unwrap();
-    let end = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+    let end = NaiveDate::from_ymd_opt(2025, 6, 30).unwrap();
     let symbol = "ETHUSDT";
-    let frequency = "monthly";
-    let interval = "1h";
-
-    let date_range = generate_date_range(start, end);
-
-    binance::historical::retrieve_and_save_historical_data_range(
-        date_range, frequency, symbol, interval,
-    )
-    .await?;
-
-    let base_dir = format!("data/spot/{frequency}/klines/{symbol}/{interval}");
-
-    // TODO: Don't simply load all files in the directory.
-    // Rather allow the user to specify the range? Maybe
-    // create one struct that allows for the files
-    // to be both downloaded and loaded?
-
-    let file_collection = identify_files(base_dir)?;
-    let mut prices = Vec::new();
-    for file_dir in file_collection {
-        let content = read_csv_from_zip_file(file_dir.as_path()).await?;
-        for line in content.lines() {
-            let entries = HistoricalKlineEvent::from_delimited_string(&line, ',')?;
-            prices.push(entries);
+    let interval = KlineInterval::OneHour;
+
+    let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+    let progress_handle = tokio::spawn(async move {
+        while let Some(progress) = progress_rx.recv().await {
+            println!("{progress}");
         }
-    }
+    });
 
-    prices.sort();
+    let options = DownloadOptions {
+        progress: Some(progress_tx),
+        ..Default::default()
+    };
+
+    let (prices, reports) = KlineDataset::new(symbol, interval)
+        .range(start, end)
+        .download_options(options)
+        .load_with_reports()
+        .await?;
+
+    // The progress channel is closed once the dataset (and
+    // with it the download options) is dropped.
+    progress_handle.await?;
+
+    for (frequency, report) in reports {
+        for date in report.missing {
+            println!("Archive not available: {symbol} {interval} {frequency} {date}");
+        }
+        for (date, e) in report.failed {
+            println!("Failed to download: {symbol} {interval} {frequency} {date}: {e}");
+        }
+    }
 
     let mut position = PositionParameters::default();
     let mut strategy = SimpleAverage::default();
     let mut book = TrackPositionMovement::default();
 
-    for trade in prices {
+    for trade in prices.klines {
         strategy.handle_stream_event(&trade.clone().into())?;
         position.set_action(strategy.determine_action(position.direction()));
 
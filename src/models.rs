@@ -1,23 +1,10 @@
-use crate::{errors, fs::parse::string_to_f64};
+use crate::errors;
+use crate::fs::parse::{binance_timestamp_to_milliseconds, string_to_f64};
 use serde::Deserialize;
 use std::str::FromStr;
 
-// TODO: <symbol>@ticker
-// 24hr rolling window ticker statistics for a single symbol.
-// These are NOT the statistics of the UTC day, but a 24hr rolling window for the previous 24hrs.
-
-// TODO: <symbol>@ticker_<window_size>
-// Rolling window ticker statistics for a single symbol, computed over multiple windows.
-
-// TODO: <symbol>@avgPrice
-// Average price streams push changes in the average price over a fixed time interval.
-
-// TODO: <symbol>@depth OR <symbol>@depth@100ms
-// Order book price and quantity depth updates used to locally manage an order book.
-
-// TODO: How to manage a local order book correctly
-
 // Deserialize klines downloaded from data.binances.vision
+// Timestamps are in milliseconds, regardless of the unit used in the archive.
 #[allow(non_snake_case)]
 #[derive(Debug, Deserialize, Clone)]
 pub struct HistoricalKlineEvent {
@@ -119,20 +106,195 @@ pub struct Kline {
     B: String, // Unused, can be ignored
 }
 
+/// Deserialize trades received using binance websocket (`<symbol>@trade`).
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct TradeEvent {
+    pub e: String, // Event type
+    pub E: i64,    // Event time
+    pub s: String, // Symbol
+    pub t: u64,    // Trade ID
+    #[serde(deserialize_with = "string_to_f64")]
+    pub p: f64, // Price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub q: f64, // Quantity
+    pub T: i64,    // Trade time
+    pub m: bool,   // Is the buyer the market maker?
+}
+
+/// Deserialize aggregate trades received using binance websocket (`<symbol>@aggTrade`).
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct AggTradeEvent {
+    pub e: String, // Event type
+    pub E: i64,    // Event time
+    pub s: String, // Symbol
+    pub a: u64,    // Aggregate trade ID
+    #[serde(deserialize_with = "string_to_f64")]
+    pub p: f64, // Price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub q: f64, // Quantity
+    pub f: u64,    // First trade ID
+    pub l: u64,    // Last trade ID
+    pub T: i64,    // Trade time
+    pub m: bool,   // Is the buyer the market maker?
+}
+
+/// Deserialize 24hr rolling window mini tickers received using
+/// binance websocket (`<symbol>@miniTicker`).
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct MiniTickerEvent {
+    pub e: String, // Event type
+    pub E: i64,    // Event time
+    pub s: String, // Symbol
+    #[serde(deserialize_with = "string_to_f64")]
+    pub c: f64, // Close price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub o: f64, // Open price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub h: f64, // High price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub l: f64, // Low price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub v: f64, // Total traded base asset volume
+    #[serde(deserialize_with = "string_to_f64")]
+    pub q: f64, // Total traded quote asset volume
+}
+
+/// Deserialize 24hr rolling window tickers received using binance
+/// websocket (`<symbol>@ticker`). These are NOT the statistics of the
+/// UTC day, but of the previous 24hrs.
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct TickerEvent {
+    pub e: String, // Event type
+    pub E: i64,    // Event time
+    pub s: String, // Symbol
+    #[serde(deserialize_with = "string_to_f64")]
+    pub p: f64, // Price change
+    #[serde(deserialize_with = "string_to_f64")]
+    pub P: f64, // Price change percent
+    #[serde(deserialize_with = "string_to_f64")]
+    pub w: f64, // Weighted average price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub x: f64, // First trade before the 24hr rolling window
+    #[serde(deserialize_with = "string_to_f64")]
+    pub c: f64, // Last price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub Q: f64, // Last quantity
+    #[serde(deserialize_with = "string_to_f64")]
+    pub b: f64, // Best bid price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub B: f64, // Best bid quantity
+    #[serde(deserialize_with = "string_to_f64")]
+    pub a: f64, // Best ask price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub A: f64, // Best ask quantity
+    #[serde(deserialize_with = "string_to_f64")]
+    pub o: f64, // Open price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub h: f64, // High price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub l: f64, // Low price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub v: f64, // Total traded base asset volume
+    #[serde(deserialize_with = "string_to_f64")]
+    pub q: f64, // Total traded quote asset volume
+    pub O: i64,    // Statistics open time
+    pub C: i64,    // Statistics close time
+    pub F: i64,    // First trade ID
+    pub L: i64,    // Last trade ID
+    pub n: u64,    // Total number of trades
+}
+
+/// Deserialize rolling window tickers received using binance
+/// websocket (`<symbol>@ticker_<window_size>`).
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct RollingWindowTickerEvent {
+    pub e: String, // Event type, e.g. "1hTicker"
+    pub E: i64,    // Event time
+    pub s: String, // Symbol
+    #[serde(deserialize_with = "string_to_f64")]
+    pub p: f64, // Price change
+    #[serde(deserialize_with = "string_to_f64")]
+    pub P: f64, // Price change percent
+    #[serde(deserialize_with = "string_to_f64")]
+    pub o: f64, // Open price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub h: f64, // High price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub l: f64, // Low price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub c: f64, // Last price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub w: f64, // Weighted average price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub v: f64, // Total traded base asset volume
+    #[serde(deserialize_with = "string_to_f64")]
+    pub q: f64, // Total traded quote asset volume
+    pub O: i64,    // Statistics open time
+    pub C: i64,    // Statistics close time
+    pub F: i64,    // First trade ID
+    pub L: i64,    // Last trade ID
+    pub n: u64,    // Total number of trades
+}
+
+/// Deserialize average prices received using binance websocket (`<symbol>@avgPrice`).
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct AvgPriceEvent {
+    pub e: String, // Event type
+    pub E: i64,    // Event time
+    pub s: String, // Symbol
+    pub i: String, // Average price interval, e.g. "5m"
+    #[serde(deserialize_with = "string_to_f64")]
+    pub w: f64, // Average price
+    pub T: i64,    // Last trade time
+}
+
+/// Deserialize best bid and ask updates received using binance
+/// websocket (`<symbol>@bookTicker`).
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct BookTickerEvent {
+    pub u: u64,    // Order book update ID
+    pub s: String, // Symbol
+    #[serde(deserialize_with = "string_to_f64")]
+    pub b: f64, // Best bid price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub B: f64, // Best bid quantity
+    #[serde(deserialize_with = "string_to_f64")]
+    pub a: f64, // Best ask price
+    #[serde(deserialize_with = "string_to_f64")]
+    pub A: f64, // Best ask quantity
+}
+
+/// Splits a line into at most `N` fields without allocating, returning
+/// the fields along with the number of fields found.
+fn split_fields<const N: usize>(
+    line: &str,
+    delimiter: char,
+) -> Result<([&str; N], usize), errors::Error> {
+    let mut fields = [""; N];
+    let mut len = 0;
+    for field in line.split(delimiter) {
+        if len == N {
+            return Err("Length of line not equal to expected length".into());
+        }
+        fields[len] = field;
+        len += 1;
+    }
+    Ok((fields, len))
+}
+
 /// Trait used to serialize string in the absence of field names
 /// i.e. when serde can't be used.
 pub trait FromDelimitedString<A>
 where
     Self: Sized,
 {
-    fn parse_field<T: FromStr>(fields: &[&str], index: usize) -> Result<T, errors::Error>
-    where
-        T::Err: std::fmt::Display;
-
-    fn from_delimited_string(line: A, delimiter: char) -> Result<Self, errors::Error>;
-}
-
-impl FromDelimitedString<&str> for HistoricalKlineEvent {
     fn parse_field<T: FromStr>(fields: &[&str], index: usize) -> Result<T, errors::Error>
     where
         T::Err: std::fmt::Display,
@@ -145,24 +307,304 @@ impl FromDelimitedString<&str> for HistoricalKlineEvent {
             .map_err(|e| errors::Error::Parse(format!("Failed to parse field {}: {}", index, e)))
     }
 
+    /// Parses timestamp fields, normalised to milliseconds since Binance
+    /// archives switched to microseconds in 2025.
+    fn parse_timestamp_field(fields: &[&str], index: usize) -> Result<i64, errors::Error> {
+        Self::parse_field::<i64>(fields, index).map(binance_timestamp_to_milliseconds)
+    }
+
+    /// Parses boolean fields, which are capitalised (i.e. `True`/`False`)
+    /// in the archives.
+    fn parse_bool_field(fields: &[&str], index: usize) -> Result<bool, errors::Error> {
+        let field = fields
+            .get(index)
+            .ok_or_else(|| errors::Error::Other(format!("Unable to retrieve index {}", index)))?;
+        if field.eq_ignore_ascii_case("true") {
+            Ok(true)
+        } else if field.eq_ignore_ascii_case("false") {
+            Ok(false)
+        } else {
+            Err(errors::Error::Parse(format!(
+                "Failed to parse field {}: invalid boolean {}",
+                index, field
+            )))
+        }
+    }
+
+    /// Parses boolean fields that are absent from some archives.
+    fn parse_optional_bool_field(
+        fields: &[&str],
+        index: usize,
+    ) -> Result<Option<bool>, errors::Error> {
+        if index >= fields.len() {
+            return Ok(None);
+        }
+        Self::parse_bool_field(fields, index).map(Some)
+    }
+
+    fn from_delimited_string(line: A, delimiter: char) -> Result<Self, errors::Error>;
+}
+
+impl FromDelimitedString<&str> for HistoricalKlineEvent {
     fn from_delimited_string(line: &str, delimiter: char) -> Result<Self, errors::Error> {
-        let splitted_line: Vec<&str> = line.split(delimiter).collect();
+        let (fields, len) = split_fields::<12>(line, delimiter)?;
+        let splitted_line = &fields[..len];
         if splitted_line.len() != 12 {
             return Err(format!("Length of line not equal to expected length").into());
         }
         Ok(Self {
-            t: Self::parse_field(&splitted_line, 0)?,
-            o: Self::parse_field(&splitted_line, 1)?,
-            h: Self::parse_field(&splitted_line, 2)?,
-            l: Self::parse_field(&splitted_line, 3)?,
-            c: Self::parse_field(&splitted_line, 4)?,
-            v: Self::parse_field(&splitted_line, 5)?,
-            T: Self::parse_field(&splitted_line, 6)?,
-            q: Self::parse_field(&splitted_line, 7)?,
-            n: Self::parse_field(&splitted_line, 8)?,
-            V: Self::parse_field(&splitted_line, 9)?,
-            Q: Self::parse_field(&splitted_line, 10)?,
-            B: Self::parse_field(&splitted_line, 11)?,
+            t: Self::parse_timestamp_field(splitted_line, 0)?,
+            o: Self::parse_field(splitted_line, 1)?,
+            h: Self::parse_field(splitted_line, 2)?,
+            l: Self::parse_field(splitted_line, 3)?,
+            c: Self::parse_field(splitted_line, 4)?,
+            v: Self::parse_field(splitted_line, 5)?,
+            T: Self::parse_timestamp_field(splitted_line, 6)?,
+            q: Self::parse_field(splitted_line, 7)?,
+            n: Self::parse_field(splitted_line, 8)?,
+            V: Self::parse_field(splitted_line, 9)?,
+            Q: Self::parse_field(splitted_line, 10)?,
+            B: Self::parse_field(splitted_line, 11)?,
+        })
+    }
+}
+
+// Klines retrieved from the REST API (`/api/v3/klines`) are arrays of
+// mostly string values, ordered identically to the archive columns.
+impl TryFrom<Vec<serde_json::Value>> for HistoricalKlineEvent {
+    type Error = errors::Error;
+
+    fn try_from(values: Vec<serde_json::Value>) -> Result<Self, Self::Error> {
+        let line = values
+            .iter()
+            .map(|value| match value {
+                serde_json::Value::String(s) => s.clone(),
+                value => value.to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+        Self::from_delimited_string(&line, ',')
+    }
+}
+
+// Deserialize aggregate trades downloaded from data.binances.vision
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct AggTrade {
+    pub a: u64,          // Aggregate trade ID
+    pub p: f64,          // Price
+    pub q: f64,          // Quantity
+    pub f: u64,          // First trade ID
+    pub l: u64,          // Last trade ID
+    pub T: i64,          // Trade time
+    pub m: bool,         // Is the buyer the market maker?
+    pub M: Option<bool>, // Was the trade the best price match? (spot only)
+}
+
+impl FromDelimitedString<&str> for AggTrade {
+    fn from_delimited_string(line: &str, delimiter: char) -> Result<Self, errors::Error> {
+        let (fields, len) = split_fields::<8>(line, delimiter)?;
+        let splitted_line = &fields[..len];
+        // Futures archives omit the best price match column.
+        if !(7..=8).contains(&splitted_line.len()) {
+            return Err("Length of line not equal to expected length".into());
+        }
+        Ok(Self {
+            a: Self::parse_field(splitted_line, 0)?,
+            p: Self::parse_field(splitted_line, 1)?,
+            q: Self::parse_field(splitted_line, 2)?,
+            f: Self::parse_field(splitted_line, 3)?,
+            l: Self::parse_field(splitted_line, 4)?,
+            T: Self::parse_timestamp_field(splitted_line, 5)?,
+            m: Self::parse_bool_field(splitted_line, 6)?,
+            M: Self::parse_optional_bool_field(splitted_line, 7)?,
+        })
+    }
+}
+
+// Deserialize trades downloaded from data.binances.vision
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct Trade {
+    pub t: u64,          // Trade ID
+    pub p: f64,          // Price
+    pub q: f64,          // Quantity
+    pub Q: f64,          // Quote asset quantity
+    pub T: i64,          // Trade time
+    pub m: bool,         // Is the buyer the market maker?
+    pub M: Option<bool>, // Was the trade the best price match? (spot only)
+}
+
+impl FromDelimitedString<&str> for Trade {
+    fn from_delimited_string(line: &str, delimiter: char) -> Result<Self, errors::Error> {
+        let (fields, len) = split_fields::<7>(line, delimiter)?;
+        let splitted_line = &fields[..len];
+        // Futures archives omit the best price match column.
+        if !(6..=7).contains(&splitted_line.len()) {
+            return Err("Length of line not equal to expected length".into());
+        }
+        Ok(Self {
+            t: Self::parse_field(splitted_line, 0)?,
+            p: Self::parse_field(splitted_line, 1)?,
+            q: Self::parse_field(splitted_line, 2)?,
+            Q: Self::parse_field(splitted_line, 3)?,
+            T: Self::parse_timestamp_field(splitted_line, 4)?,
+            m: Self::parse_bool_field(splitted_line, 5)?,
+            M: Self::parse_optional_bool_field(splitted_line, 6)?,
+        })
+    }
+}
+
+// Deserialize mark price, index price and premium index klines downloaded
+// from data.binances.vision. These share the layout of regular klines,
+// however the volume and trade count columns are unused.
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct PriceKline {
+    pub t: i64, // Kline start time
+    pub o: f64, // Open price
+    pub h: f64, // High price
+    pub l: f64, // Low price
+    pub c: f64, // Close price
+    pub T: i64, // Kline close time
+}
+
+impl FromDelimitedString<&str> for PriceKline {
+    fn from_delimited_string(line: &str, delimiter: char) -> Result<Self, errors::Error> {
+        let (fields, len) = split_fields::<12>(line, delimiter)?;
+        let splitted_line = &fields[..len];
+        if splitted_line.len() != 12 {
+            return Err("Length of line not equal to expected length".into());
+        }
+        Ok(Self {
+            t: Self::parse_timestamp_field(splitted_line, 0)?,
+            o: Self::parse_field(splitted_line, 1)?,
+            h: Self::parse_field(splitted_line, 2)?,
+            l: Self::parse_field(splitted_line, 3)?,
+            c: Self::parse_field(splitted_line, 4)?,
+            T: Self::parse_timestamp_field(splitted_line, 6)?,
+        })
+    }
+}
+
+// Deserialize futures funding rates downloaded from data.binances.vision
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct FundingRate {
+    pub T: i64, // Funding calculation time
+    pub i: u32, // Funding interval (hours)
+    pub r: f64, // Funding rate
+}
+
+impl FromDelimitedString<&str> for FundingRate {
+    fn from_delimited_string(line: &str, delimiter: char) -> Result<Self, errors::Error> {
+        let (fields, len) = split_fields::<3>(line, delimiter)?;
+        let splitted_line = &fields[..len];
+        if splitted_line.len() != 3 {
+            return Err("Length of line not equal to expected length".into());
+        }
+        Ok(Self {
+            T: Self::parse_timestamp_field(splitted_line, 0)?,
+            i: Self::parse_field(splitted_line, 1)?,
+            r: Self::parse_field(splitted_line, 2)?,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1 June 2024 00:00 UTC, archived with millisecond timestamps.
+    const ROW_2024: &str = "1717200000000,67472.41,67520.00,67400.00,67493.99,512.33,1717203599999,34573829.11,28571,256.41,17303921.09,0";
+    // 1 June 2025 00:00 UTC, archived with microsecond timestamps.
+    const ROW_2025: &str = "1748736000000000,104591.88,104760.00,104500.01,104700.00,301.21,1748739599999999,31520043.67,41234,150.37,15737712.50,0";
+
+    #[test]
+    fn split_fields_up_to_capacity() {
+        let (fields, len) = split_fields::<4>("a,b,c", ',').unwrap();
+        assert_eq!(&fields[..len], &["a", "b", "c"]);
+
+        let (fields, len) = split_fields::<3>("a,,c", ',').unwrap();
+        assert_eq!(&fields[..len], &["a", "", "c"]);
+    }
+
+    #[test]
+    fn split_fields_beyond_capacity() {
+        assert!(split_fields::<2>("a,b,c", ',').is_err());
+    }
+
+    #[test]
+    fn kline_with_wrong_number_of_fields() {
+        let too_many = format!("{ROW_2024},1");
+        assert!(HistoricalKlineEvent::from_delimited_string(&too_many, ',').is_err());
+
+        let too_few = ROW_2024.rsplit_once(',').unwrap().0;
+        assert!(HistoricalKlineEvent::from_delimited_string(too_few, ',').is_err());
+    }
+
+    #[test]
+    fn trades_with_optional_column() {
+        // Spot archives include the best price match column, futures do not.
+        let spot = AggTrade::from_delimited_string(
+            "3000000,67472.41,0.5,4000000,4000002,1717200000000,True,True",
+            ',',
+        )
+        .unwrap();
+        assert_eq!(spot.M, Some(true));
+        let futures = AggTrade::from_delimited_string(
+            "3000000,67472.41,0.5,4000000,4000002,1717200000000,false",
+            ',',
+        )
+        .unwrap();
+        assert_eq!(futures.M, None);
+        assert!(
+            AggTrade::from_delimited_string(
+                "3000000,67472.41,0.5,4000000,4000002,1717200000000",
+                ','
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn kline_with_millisecond_timestamps() {
+        let kline = HistoricalKlineEvent::from_delimited_string(ROW_2024, ',').unwrap();
+        assert_eq!(kline.t, 1717200000000);
+        assert_eq!(kline.T, 1717203599999);
+        assert_eq!(kline.o, 67472.41);
+        assert_eq!(kline.c, 67493.99);
+        assert_eq!(kline.n, 28571);
+    }
+
+    #[test]
+    fn kline_with_microsecond_timestamps() {
+        let kline = HistoricalKlineEvent::from_delimited_string(ROW_2025, ',').unwrap();
+        assert_eq!(kline.t, 1748736000000);
+        assert_eq!(kline.T, 1748739599999);
+        assert_eq!(kline.h, 104760.0);
+        assert_eq!(kline.n, 41234);
+    }
+
+    #[test]
+    fn klines_of_both_eras_sort_chronologically() {
+        // 1 January 2025 00:00 UTC, the first hour archived in microseconds,
+        // is preceded by the last hour of 2024 archived in milliseconds.
+        let last_2024 = "1735686000000,93500.00,93600.00,93400.00,93576.00,400.10,1735689599999,37430000.00,30000,200.05,18715000.00,0";
+        let first_2025 = "1735689600000000,93576.00,94000.00,93500.00,93900.00,420.20,1735693199999999,39400000.00,31000,210.10,19700000.00,0";
+
+        let mut klines: Vec<HistoricalKlineEvent> = [ROW_2025, first_2025, ROW_2024, last_2024]
+            .iter()
+            .map(|row| HistoricalKlineEvent::from_delimited_string(row, ',').unwrap())
+            .collect();
+        klines.sort();
+
+        let open_times: Vec<i64> = klines.iter().map(|kline| kline.t).collect();
+        assert_eq!(
+            open_times,
+            vec![1717200000000, 1735686000000, 1735689600000, 1748736000000]
+        );
+        // Consecutive hours remain contiguous across the change of unit.
+        assert_eq!(klines[1].T + 1, klines[2].t);
+    }
+}